@@ -0,0 +1,193 @@
+use flate2::read::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use tokio::sync::mpsc;
+
+// file_download：下载日志文件或其中的一段字节，可选即时 gzip 压缩。
+// 请求 {"cmd": "file_download", "file_path": ..., "offset": B, "length": L, "gzip": true}，
+// offset / length 省略时下载整个文件。响应依次为：
+//   文本帧 {"cmd": "file_download", "file_size", "offset", "length", "gzip", ...}
+//   若干二进制帧，每帧最多 CHUNK_SIZE 字节
+//   文本帧 {"cmd": "file_download", "done": true, "bytes_sent", "sha256"}，sha256 为实际发送内容的校验和
+
+pub(crate) const CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DownloadRange {
+    offset: u64,
+    length: Option<u64>,
+    gzip: bool,
+}
+
+impl DownloadRange {
+    pub(crate) fn from_request(request: &Value) -> Result<DownloadRange, String> {
+        let field = |name: &str| match &request[name] {
+            Value::Null => Ok(None),
+            value => value
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| format!("{} must be a non-negative integer", name)),
+        };
+        let offset = field("offset")?.unwrap_or(0);
+        let length = field("length")?;
+        let gzip = match &request["gzip"] {
+            Value::Null => false,
+            Value::Bool(gzip) => *gzip,
+            _ => return Err("gzip must be a boolean".to_string()),
+        };
+        Ok(DownloadRange { offset, length, gzip })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct DownloadHeader {
+    pub(crate) file_size: u64,
+    pub(crate) offset: u64,
+    pub(crate) length: u64, // 实际下载的原始字节数（压缩前）
+    pub(crate) gzip: bool,
+    pub(crate) chunk_size: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct DownloadSummary {
+    pub(crate) bytes_sent: u64,
+    pub(crate) sha256: String,
+}
+
+// 打开文件并定位到 offset，length 超出文件末尾时截到文件末尾
+pub(crate) fn open(path: &str, range: DownloadRange) -> io::Result<(DownloadHeader, Box<dyn Read + Send>)> {
+    let mut file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    if range.offset > file_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("offset {} is beyond the end of the file ({} bytes)", range.offset, file_size),
+        ));
+    }
+    let length = range.length.unwrap_or(u64::MAX).min(file_size - range.offset);
+    file.seek(SeekFrom::Start(range.offset))?;
+    let reader = file.take(length);
+    let reader: Box<dyn Read + Send> = if range.gzip {
+        Box::new(GzEncoder::new(reader, Compression::fast()))
+    } else {
+        Box::new(reader)
+    };
+    let header = DownloadHeader { file_size, offset: range.offset, length, gzip: range.gzip, chunk_size: CHUNK_SIZE };
+    Ok((header, reader))
+}
+
+// 在阻塞线程中按块读取并通过 chunks 发送；接收端关闭（客户端断开）时停止读取
+pub(crate) fn stream_blocking(mut reader: Box<dyn Read + Send>, chunks: mpsc::Sender<Vec<u8>>) -> io::Result<DownloadSummary> {
+    let mut hasher = Sha256::new();
+    let mut bytes_sent = 0;
+    loop {
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        let mut filled = 0;
+        while filled < CHUNK_SIZE {
+            let n = reader.read(&mut chunk[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled == 0 {
+            break;
+        }
+        chunk.truncate(filled);
+        hasher.update(&chunk);
+        bytes_sent += filled as u64;
+        if chunks.blocking_send(chunk).is_err() {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"));
+        }
+    }
+    Ok(DownloadSummary { bytes_sent, sha256: hex::encode(hasher.finalize()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+    use std::thread;
+
+    fn range(request: Value) -> DownloadRange {
+        DownloadRange::from_request(&request).unwrap()
+    }
+
+    // 在另一个线程中发送，返回收到的全部字节和发送端的汇总
+    fn collect(reader: Box<dyn Read + Send>) -> (Vec<u8>, DownloadSummary) {
+        let (tx, mut rx) = mpsc::channel(4);
+        let sender = thread::spawn(move || stream_blocking(reader, tx));
+        let mut received = Vec::new();
+        while let Some(chunk) = rx.blocking_recv() {
+            assert!(chunk.len() <= CHUNK_SIZE);
+            received.extend(chunk);
+        }
+        (received, sender.join().unwrap().unwrap())
+    }
+
+    fn write_file(dir: &tempfile::TempDir) -> (String, Vec<u8>) {
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 1000).map(|n| b"0123456789\n"[n % 11]).collect();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, &content).unwrap();
+        (path.to_str().unwrap().to_string(), content)
+    }
+
+    #[test]
+    fn clamps_length_and_rejects_offset_past_eof() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, content) = write_file(&dir);
+        let size = content.len() as u64;
+
+        let (header, _) = open(&path, range(json!({ "offset": 100, "length": size }))).unwrap();
+        assert_eq!((header.file_size, header.offset, header.length), (size, 100, size - 100));
+        let (header, _) = open(&path, range(json!({}))).unwrap();
+        assert_eq!(header.length, size);
+        // offset 恰好在文件末尾时下载 0 字节
+        let (header, _) = open(&path, range(json!({ "offset": size }))).unwrap();
+        assert_eq!(header.length, 0);
+
+        let error = open(&path, range(json!({ "offset": size + 1 }))).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(DownloadRange::from_request(&json!({ "gzip": "yes" })).is_err());
+        assert!(DownloadRange::from_request(&json!({ "length": -1 })).is_err());
+    }
+
+    // sha256 是实际发送内容（压缩时为压缩后）的校验和
+    #[test]
+    fn checksum_matches_bytes_sent() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, content) = write_file(&dir);
+        let expected = &content[10..10 + CHUNK_SIZE + 500];
+
+        let (_, reader) = open(&path, range(json!({ "offset": 10, "length": CHUNK_SIZE + 500 }))).unwrap();
+        let (received, summary) = collect(reader);
+        assert_eq!(received, expected);
+        assert_eq!(summary.bytes_sent, received.len() as u64);
+        assert_eq!(summary.sha256, hex::encode(Sha256::digest(&received)));
+
+        let (header, reader) = open(&path, range(json!({ "offset": 10, "length": CHUNK_SIZE + 500, "gzip": true }))).unwrap();
+        assert!(header.gzip);
+        let (received, summary) = collect(reader);
+        assert_eq!(summary.bytes_sent, received.len() as u64);
+        assert_eq!(summary.sha256, hex::encode(Sha256::digest(&received)));
+        let mut decompressed = Vec::new();
+        GzDecoder::new(received.as_slice()).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, expected);
+    }
+
+    // 无限长的输入在接收端关闭后也会停止
+    #[test]
+    fn stops_when_receiver_is_dropped() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let sender = thread::spawn(move || stream_blocking(Box::new(io::repeat(b'x')), tx));
+        assert_eq!(rx.blocking_recv().unwrap().len(), CHUNK_SIZE);
+        drop(rx);
+        let error = sender.join().unwrap().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,348 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use log::warn;
+use crate::grep::open_reader;
+use crate::line_index::{self, LineIndex};
+use crate::timestamp::TimestampParser;
+
+// file_read：按行号或字节偏移读取日志文件的一段，用于在前端翻页查看原始文件。
+// 三种请求方式（行号从 1 开始）：
+//   {"line": N, "context": K}            以第 N 行为中心，前后各 K 行
+//   {"start_line": N, "line_count": M}   从第 N 行开始读取 M 行
+//   {"offset": B, "length": L}           从字节偏移 B 开始读取约 L 字节（对齐到整行）
+// 翻页时把上次返回的 end_offset 作为下一次的 offset。大文件通过行索引直接定位到行号附近，
+// 字节偏移模式下也由行索引换算出行号；.gz 文件没有索引，字节偏移模式下不返回行号。
+
+const DEFAULT_CONTEXT: u64 = 20;
+const DEFAULT_LINE_COUNT: u64 = 200;
+const DEFAULT_LENGTH: u64 = 64 * 1024;
+const MAX_LINES: u64 = 5000;
+const MAX_BYTES: u64 = 1024 * 1024; // 单次返回的最大字节数，超出时截断并由 eof = false 表示还有后续
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ReadRange {
+    Lines { start: u64, count: u64, focus: Option<u64> },
+    Bytes { offset: u64, length: u64 },
+}
+
+fn field_u64(request: &Value, field: &str) -> Result<Option<u64>, String> {
+    match &request[field] {
+        Value::Null => Ok(None),
+        value => value
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| format!("{} must be a non-negative integer", field)),
+    }
+}
+
+impl ReadRange {
+    pub(crate) fn from_request(request: &Value) -> Result<ReadRange, String> {
+        if let Some(line) = field_u64(request, "line")? {
+            if line == 0 {
+                return Err("line starts at 1".to_string());
+            }
+            let context = field_u64(request, "context")?.unwrap_or(DEFAULT_CONTEXT).min(MAX_LINES / 2);
+            let start = line.saturating_sub(context).max(1);
+            return Ok(ReadRange::Lines { start, count: line + context - start + 1, focus: Some(line) });
+        }
+        if let Some(start) = field_u64(request, "start_line")? {
+            if start == 0 {
+                return Err("start_line starts at 1".to_string());
+            }
+            let count = field_u64(request, "line_count")?.unwrap_or(DEFAULT_LINE_COUNT).clamp(1, MAX_LINES);
+            return Ok(ReadRange::Lines { start, count, focus: None });
+        }
+        let offset = field_u64(request, "offset")?.unwrap_or(0);
+        let length = field_u64(request, "length")?.unwrap_or(DEFAULT_LENGTH).clamp(1, MAX_BYTES);
+        Ok(ReadRange::Bytes { offset, length })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct FileChunk {
+    pub(crate) file_size: u64,           // 文件在磁盘上的大小（.gz 文件为压缩后大小）
+    pub(crate) start_offset: u64,        // 第一行在（解压后）文件中的字节偏移
+    pub(crate) end_offset: u64,          // 最后一行结束后的偏移，即下一页的 offset
+    pub(crate) start_line: Option<u64>,  // 第一行的行号，无法确定时为 null
+    pub(crate) focus_line: Option<u64>,
+    pub(crate) total_lines: Option<u64>, // 文件总行数，.gz 文件为 null
+    pub(crate) lines: Vec<String>,
+    pub(crate) eof: bool,
+}
+
+fn read_line(reader: &mut dyn BufRead, buf: &mut Vec<u8>) -> io::Result<usize> {
+    buf.clear();
+    reader.read_until(b'\n', buf)
+}
+
+fn push_line(lines: &mut Vec<String>, buf: &[u8]) {
+    let line = String::from_utf8_lossy(buf);
+    lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+}
+
+fn read_lines(path: &str, start: u64, count: u64, index: Option<&LineIndex>) -> io::Result<(u64, Vec<String>, u64, bool)> {
+    let (mut reader, mut offset, first_line): (Box<dyn BufRead>, u64, u64) = match index.and_then(|index| index.block_for_line(start)) {
+        Some(block) => {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(block.offset))?;
+            (Box::new(BufReader::new(file)), block.offset, block.line)
+        }
+        None => (open_reader(path)?, 0, 1),
+    };
+    let mut buf = Vec::new();
+    for _ in first_line..start {
+        let n = read_line(&mut *reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        offset += n as u64;
+    }
+
+    let mut lines = Vec::new();
+    let mut read = 0;
+    while (lines.len() as u64) < count && read < MAX_BYTES {
+        let n = read_line(&mut *reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        push_line(&mut lines, &buf);
+    }
+    let eof = reader.fill_buf()?.is_empty();
+    Ok((offset, lines, offset + read, eof))
+}
+
+// 从 offset 所在行的下一个行首开始读取；offset 恰好是行首时从该行开始
+fn read_bytes(path: &str, offset: u64, length: u64) -> io::Result<(u64, Vec<String>, u64, bool)> {
+    let mut reader: Box<dyn BufRead> = if offset > 0 && !path.ends_with(".gz") {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset - 1))?;
+        Box::new(BufReader::new(file))
+    } else {
+        let mut reader = open_reader(path)?;
+        io::copy(&mut (&mut reader).take(offset.saturating_sub(1)), &mut io::sink())?;
+        reader
+    };
+    let mut buf = Vec::new();
+    let mut start = offset;
+    if offset > 0 {
+        start = offset - 1 + read_line(&mut *reader, &mut buf)? as u64;
+    }
+
+    let mut lines = Vec::new();
+    let mut read = 0;
+    while read < length.min(MAX_BYTES) {
+        let n = read_line(&mut *reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        push_line(&mut lines, &buf);
+    }
+    let eof = reader.fill_buf()?.is_empty();
+    Ok((start, lines, start + read, eof))
+}
+
+// 未建立索引的小文件直接统计前 limit 字节中的换行符
+fn count_newlines(path: &str, limit: u64) -> io::Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?.take(limit));
+    let mut lines = 0;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(lines);
+        }
+        lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        let n = buf.len();
+        reader.consume(n);
+    }
+}
+
+fn read_chunk_blocking(path: &str, range: ReadRange, parser: Option<TimestampParser>) -> io::Result<FileChunk> {
+    let file_size = fs::metadata(path)?.len();
+    let index = line_index::load(path, parser.as_ref(), true).unwrap_or_else(|e| {
+        warn!("Failed to build line index for {}: {}", path, e);
+        None
+    });
+    let index = index.as_deref();
+    let total_lines = match index {
+        Some(index) => Some(index.total_lines),
+        None if path.ends_with(".gz") => None,
+        None => Some(count_newlines(path, u64::MAX)?),
+    };
+
+    let (start_offset, lines, end_offset, eof, start_line, focus_line) = match range {
+        ReadRange::Lines { start, count, focus } => {
+            let (start_offset, lines, end_offset, eof) = read_lines(path, start, count, index)?;
+            (start_offset, lines, end_offset, eof, Some(start), focus)
+        }
+        ReadRange::Bytes { offset, length } => {
+            let (start_offset, lines, end_offset, eof) = read_bytes(path, offset, length)?;
+            let start_line = match index {
+                Some(index) => index.line_at(start_offset)?,
+                None if path.ends_with(".gz") => None,
+                None => Some(count_newlines(path, start_offset)? + 1),
+            };
+            (start_offset, lines, end_offset, eof, start_line, None)
+        }
+    };
+    Ok(FileChunk { file_size, start_offset, end_offset, start_line, focus_line, total_lines, lines, eof })
+}
+
+pub(crate) async fn read_chunk(path: String, range: ReadRange, parser: Option<TimestampParser>) -> io::Result<FileChunk> {
+    tokio::task::spawn_blocking(move || read_chunk_blocking(&path, range, parser))
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+    use std::io::Write;
+
+    // 第 n 行为 "line n"
+    fn write_log(path: &std::path::Path, count: u64) -> Vec<u8> {
+        let content: Vec<u8> = (1..=count).flat_map(|n| format!("line {}\n", n).into_bytes()).collect();
+        fs::write(path, &content).unwrap();
+        content
+    }
+
+    fn write_gz(path: &std::path::Path, content: &[u8]) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn parses_read_ranges() {
+        let range = ReadRange::from_request(&json!({ "line": 50, "context": 5 })).unwrap();
+        assert!(matches!(range, ReadRange::Lines { start: 45, count: 11, focus: Some(50) }));
+        // 靠近文件开头时从第 1 行开始
+        let range = ReadRange::from_request(&json!({ "line": 3 })).unwrap();
+        assert!(matches!(range, ReadRange::Lines { start: 1, count: 23, focus: Some(3) }));
+
+        let range = ReadRange::from_request(&json!({ "start_line": 10, "line_count": 100_000 })).unwrap();
+        assert!(matches!(range, ReadRange::Lines { start: 10, count: MAX_LINES, focus: None }));
+
+        let range = ReadRange::from_request(&json!({})).unwrap();
+        assert!(matches!(range, ReadRange::Bytes { offset: 0, length: DEFAULT_LENGTH }));
+        let range = ReadRange::from_request(&json!({ "offset": 7, "length": 0 })).unwrap();
+        assert!(matches!(range, ReadRange::Bytes { offset: 7, length: 1 }));
+
+        assert!(ReadRange::from_request(&json!({ "line": 0 })).is_err());
+        assert!(ReadRange::from_request(&json!({ "start_line": 0 })).is_err());
+        assert!(ReadRange::from_request(&json!({ "offset": -1 })).is_err());
+        assert!(ReadRange::from_request(&json!({ "length": "10" })).is_err());
+    }
+
+    // 通过索引块定位与从头逐行读取的结果一致
+    #[test]
+    fn reads_lines_through_index_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let content = write_log(&path, 600_000);
+        let path = path.to_str().unwrap();
+        let index = line_index::load(path, None, true).unwrap().expect("file is large enough to be indexed");
+        let block = index.block_for_line(500_000).unwrap();
+        assert!(block.line > 1);
+
+        let (start, lines, end, eof) = read_lines(path, 500_000, 3, Some(&index)).unwrap();
+        assert_eq!(lines, ["line 500000", "line 500001", "line 500002"]);
+        // 第 499999 个换行符之后即第 500000 行的行首
+        let expected = content.iter().enumerate().filter(|(_, &b)| b == b'\n').nth(499_998).unwrap().0 as u64 + 1;
+        assert_eq!(start, expected);
+        assert_eq!(end, start + "line 500000\nline 500001\nline 500002\n".len() as u64);
+        assert!(!eof);
+        assert_eq!(read_lines(path, 500_000, 3, None).unwrap(), (start, lines, end, eof));
+
+        let (_, lines, end, eof) = read_lines(path, 599_999, 10, Some(&index)).unwrap();
+        assert_eq!(lines, ["line 599999", "line 600000"]);
+        assert_eq!(end, content.len() as u64);
+        assert!(eof);
+    }
+
+    // 偏移对齐到下一个行首，恰好是行首时从该行开始；.gz 文件同样按解压后的偏移
+    #[test]
+    fn aligns_byte_offsets_to_line_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("app.log");
+        let content = write_log(&plain, 20);
+        let gz = dir.path().join("app.log.gz");
+        write_gz(&gz, &content);
+
+        for path in [plain.to_str().unwrap(), gz.to_str().unwrap()] {
+            let (start, lines, _, _) = read_bytes(path, 0, 1).unwrap();
+            assert_eq!((start, lines[0].as_str()), (0, "line 1"), "{}", path);
+            // "line 1\n" 之后的行首
+            let (start, lines, _, _) = read_bytes(path, 7, 1).unwrap();
+            assert_eq!((start, lines[0].as_str()), (7, "line 2"), "{}", path);
+            let (start, lines, _, _) = read_bytes(path, 8, 1).unwrap();
+            assert_eq!((start, lines[0].as_str()), (14, "line 3"), "{}", path);
+            let (start, lines, end, eof) = read_bytes(path, content.len() as u64 + 10, 1).unwrap();
+            assert!(lines.is_empty() && eof && start == end, "{}", path);
+        }
+    }
+
+    // 以 end_offset 作为下一页的 offset，直到 eof 时读完所有行且不重复
+    #[test]
+    fn pages_with_end_offset_until_eof() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("app.log");
+        let content = write_log(&plain, 100);
+        let gz = dir.path().join("app.log.gz");
+        write_gz(&gz, &content);
+
+        for path in [plain.to_str().unwrap(), gz.to_str().unwrap()] {
+            let mut offset = 0;
+            let mut all = Vec::new();
+            let mut pages = 0;
+            loop {
+                let (start, lines, end, eof) = read_bytes(path, offset, 50).unwrap();
+                assert_eq!(start, offset, "{}", path);
+                all.extend(lines);
+                pages += 1;
+                offset = end;
+                if eof {
+                    break;
+                }
+            }
+            assert!(pages > 1);
+            assert_eq!(offset, content.len() as u64);
+            let expected: Vec<String> = (1..=100).map(|n| format!("line {}", n)).collect();
+            assert_eq!(all, expected, "{}", path);
+        }
+    }
+
+    // 字节偏移模式下由换行符数量得到行号，.gz 文件没有行号
+    #[test]
+    fn reports_start_line_in_byte_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("app.log");
+        let content = write_log(&plain, 20);
+        let gz = dir.path().join("app.log.gz");
+        write_gz(&gz, &content);
+
+        let chunk = read_chunk_blocking(plain.to_str().unwrap(), ReadRange::Bytes { offset: 8, length: 7 }, None).unwrap();
+        assert_eq!(chunk.start_offset, 14);
+        assert_eq!(chunk.start_line, Some(3));
+        assert_eq!(chunk.lines, ["line 3"]);
+        assert_eq!(chunk.total_lines, Some(20));
+        assert_eq!(chunk.file_size, content.len() as u64);
+
+        let chunk = read_chunk_blocking(gz.to_str().unwrap(), ReadRange::Bytes { offset: 8, length: 7 }, None).unwrap();
+        assert_eq!(chunk.start_offset, 14);
+        assert_eq!(chunk.start_line, None);
+        assert_eq!(chunk.total_lines, None);
+        assert_eq!(chunk.lines, ["line 3"]);
+
+        let chunk = read_chunk_blocking(plain.to_str().unwrap(), ReadRange::Lines { start: 19, count: 5, focus: None }, None).unwrap();
+        assert_eq!(chunk.start_line, Some(19));
+        assert_eq!(chunk.lines, ["line 19", "line 20"]);
+        assert!(chunk.eof);
+    }
+}
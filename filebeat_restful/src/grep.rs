@@ -0,0 +1,691 @@
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::NaiveDateTime;
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use crate::line_index;
+use crate::timestamp::{TimestampParser, DETECT_LINES};
+
+// file_grep 的匹配条件：patterns 中每一项为一层过滤，各层之间为 AND；
+// 一层可以是单个条件，也可以是 {"any": [...]} 条件组（组内 OR）。
+// 兼容旧的 filter_strings（每个字符串为一层字面量匹配，空字符串忽略）。
+// 文件名以 .gz 结尾时按 gzip 解压后扫描。
+
+const CHECK_INTERVAL: usize = 1024; // 每扫描这么多行检查一次超时和取消
+
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PatternKind {
+    #[default]
+    Literal,
+    Regex,    // 正则表达式（Rust regex 语法，与 grep -E / -P 的常用写法兼容）
+    Wildcard, // * 匹配任意字符，? 匹配单个字符
+}
+
+impl PatternKind {
+    fn name(self) -> &'static str {
+        match self {
+            PatternKind::Literal => "literal",
+            PatternKind::Regex => "regex",
+            PatternKind::Wildcard => "wildcard",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PatternSpec {
+    pattern: String,
+    #[serde(default, rename = "type")]
+    kind: PatternKind,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default)]
+    whole_word: bool,
+    #[serde(default)]
+    invert: bool, // 排除匹配的行
+}
+
+struct Matcher {
+    regex: Regex,
+    invert: bool,
+}
+
+impl Matcher {
+    fn matches(&self, line: &str) -> bool {
+        self.regex.is_match(line) != self.invert
+    }
+}
+
+pub(crate) struct LineFilter {
+    layers: Vec<Vec<Matcher>>,
+}
+
+fn compile(spec: &PatternSpec) -> Result<Matcher, String> {
+    if spec.pattern.is_empty() {
+        return Err("pattern must not be empty".to_string());
+    }
+    let mut source = match spec.kind {
+        PatternKind::Literal => regex::escape(&spec.pattern),
+        PatternKind::Regex => spec.pattern.clone(),
+        PatternKind::Wildcard => regex::escape(&spec.pattern).replace(r"\*", ".*").replace(r"\?", "."),
+    };
+    if spec.whole_word {
+        source = format!(r"\b(?:{})\b", source);
+    }
+    let regex = RegexBuilder::new(&source)
+        .case_insensitive(spec.case_insensitive)
+        .build()
+        .map_err(|e| format!("invalid {} pattern: {}", spec.kind.name(), e))?;
+    Ok(Matcher { regex, invert: spec.invert })
+}
+
+fn parse_spec(value: &Value) -> Result<PatternSpec, String> {
+    match value {
+        Value::String(pattern) => Ok(PatternSpec {
+            pattern: pattern.clone(),
+            kind: PatternKind::Literal,
+            case_insensitive: false,
+            whole_word: false,
+            invert: false,
+        }),
+        Value::Object(_) => serde_json::from_value(value.clone()).map_err(|e| e.to_string()),
+        other => Err(format!("expected a string or a pattern object, got {}", other)),
+    }
+}
+
+fn parse_layer(value: &Value, path: &str, errors: &mut Vec<String>) -> Option<Vec<Matcher>> {
+    let specs: Vec<(String, &Value)> = match value.get("any") {
+        Some(Value::Array(group)) if !group.is_empty() => group
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| (format!("{}.any[{}]", path, i), spec))
+            .collect(),
+        Some(_) => {
+            errors.push(format!("{}.any: expected a non-empty array of patterns", path));
+            return None;
+        }
+        None => vec![(path.to_string(), value)],
+    };
+
+    let mut matchers = Vec::new();
+    for (path, spec) in specs {
+        match parse_spec(spec).and_then(|spec| compile(&spec)) {
+            Ok(matcher) => matchers.push(matcher),
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+    Some(matchers)
+}
+
+impl LineFilter {
+    // 从 file_grep 请求中解析并校验匹配条件，失败时返回所有错误
+    pub(crate) fn from_request(request: &Value) -> Result<LineFilter, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut layers = Vec::new();
+
+        match (&request["patterns"], &request["filter_strings"]) {
+            (Value::Array(patterns), _) => {
+                for (i, layer) in patterns.iter().enumerate() {
+                    if let Some(matchers) = parse_layer(layer, &format!("patterns[{}]", i), &mut errors) {
+                        layers.push(matchers);
+                    }
+                }
+            }
+            (Value::Null, Value::Array(filter_strings)) => {
+                for (i, value) in filter_strings.iter().enumerate() {
+                    match value.as_str() {
+                        Some("") => {}
+                        Some(_) => {
+                            if let Some(matchers) = parse_layer(value, &format!("filter_strings[{}]", i), &mut errors) {
+                                layers.push(matchers);
+                            }
+                        }
+                        None => errors.push(format!("filter_strings[{}]: expected a string", i)),
+                    }
+                }
+            }
+            (Value::Null, Value::Null) => {}
+            _ => errors.push("patterns / filter_strings must be arrays".to_string()),
+        }
+
+        if errors.is_empty() && layers.is_empty() {
+            errors.push("at least one non-empty pattern is required".to_string());
+        }
+        if errors.is_empty() {
+            Ok(LineFilter { layers })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub(crate) fn matches(&self, line: &str) -> bool {
+        self.layers.iter().all(|layer| layer.iter().any(|m| m.matches(line)))
+    }
+}
+
+// 多行事件规则（如异常堆栈），在 log.yaml 的服务配置中设置，以下四种选其一，
+// 分别对应 Filebeat multiline 的 negate / match 组合：
+// start_pattern 匹配事件的第一行，不匹配的行并入上一个事件（negate: true, match: after）；
+// continuation_pattern 匹配需要并入上一个事件的行（negate: false, match: after）；
+// end_pattern 匹配事件的最后一行，不匹配的行并入下一个事件（negate: true, match: before）；
+// joins_next_pattern 匹配需要与下一行合并的行，如以 \ 结尾的行（negate: false, match: before）
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MultilineConfig {
+    #[serde(default)]
+    pub(crate) start_pattern: Option<String>,
+    #[serde(default)]
+    pub(crate) continuation_pattern: Option<String>,
+    #[serde(default)]
+    pub(crate) end_pattern: Option<String>,
+    #[serde(default)]
+    pub(crate) joins_next_pattern: Option<String>,
+    #[serde(default = "default_max_lines")]
+    pub(crate) max_lines: usize, // 单个事件的最大行数，与 Filebeat 的默认值一致
+}
+
+fn default_max_lines() -> usize {
+    500
+}
+
+pub(crate) struct MultilineRule {
+    regex: Regex,
+    negate: bool,
+    match_before: bool,
+    max_lines: usize,
+}
+
+impl MultilineConfig {
+    // 返回 (pattern, negate, match_before)
+    pub(crate) fn pattern(&self) -> Result<(&str, bool, bool), String> {
+        let patterns = [
+            (&self.start_pattern, true, false),
+            (&self.continuation_pattern, false, false),
+            (&self.end_pattern, true, true),
+            (&self.joins_next_pattern, false, true),
+        ];
+        let mut configured = patterns
+            .into_iter()
+            .filter_map(|(pattern, negate, match_before)| pattern.as_deref().map(|pattern| (pattern, negate, match_before)));
+        match (configured.next(), configured.next()) {
+            (Some(pattern), None) => Ok(pattern),
+            _ => Err(
+                "exactly one of start_pattern / continuation_pattern / end_pattern / joins_next_pattern is required"
+                    .to_string(),
+            ),
+        }
+    }
+
+    pub(crate) fn compile(&self) -> Result<MultilineRule, String> {
+        let (pattern, negate, match_before) = self.pattern()?;
+        let regex = Regex::new(pattern).map_err(|e| format!("invalid multiline pattern: {}", e))?;
+        Ok(MultilineRule { regex, negate, match_before, max_lines: self.max_lines.max(1) })
+    }
+}
+
+impl MultilineRule {
+    // match after 时表示该行并入上一行，match before 时表示该行与下一行合并
+    fn joins(&self, line: &str) -> bool {
+        self.regex.is_match(line) != self.negate
+    }
+}
+
+pub(crate) struct GrepRequest {
+    pub(crate) filter: LineFilter,
+    pub(crate) context: usize, // 匹配事件前后输出的事件数
+    pub(crate) window: Option<TimeWindow>,
+    pub(crate) multiline: Option<MultilineRule>,
+}
+
+// 单次 file_grep 的资源限制，可在 log.yaml 的 limits 中配置
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct GrepLimits {
+    pub(crate) max_matched_lines: u64,
+    pub(crate) max_output_bytes: usize,
+    pub(crate) max_wall_time_secs: u64,
+    pub(crate) max_concurrent_greps_per_client: usize,
+}
+
+impl Default for GrepLimits {
+    fn default() -> Self {
+        GrepLimits {
+            max_matched_lines: 10_000,
+            max_output_bytes: 16 * 1024 * 1024,
+            max_wall_time_secs: 60,
+            max_concurrent_greps_per_client: 2,
+        }
+    }
+}
+
+// 扫描提前结束的原因
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Truncation {
+    MatchedLines,
+    OutputBytes,
+    WallTime,
+    Cancelled, // 客户端已断开
+}
+
+impl Truncation {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Truncation::MatchedLines => "max_matched_lines",
+            Truncation::OutputBytes => "max_output_bytes",
+            Truncation::WallTime => "max_wall_time",
+            Truncation::Cancelled => "cancelled",
+        }
+    }
+}
+
+pub(crate) struct GrepOutput {
+    pub(crate) text: String,
+    pub(crate) bytes_scanned: u64,
+    pub(crate) matched_lines: u64,
+    pub(crate) truncated: Option<Truncation>,
+}
+
+// 按日志时间过滤：parser 为 None 时根据文件内容自动识别时间格式
+pub(crate) struct TimeWindow {
+    pub(crate) parser: Option<TimestampParser>,
+    pub(crate) start: Option<NaiveDateTime>,
+    pub(crate) end: Option<NaiveDateTime>,
+}
+
+pub(crate) fn open_reader(path: &str) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if path.ends_with(".gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+pub(crate) fn detect_parser(path: &str) -> Result<TimestampParser, String> {
+    let reader = open_reader(path).map_err(|e| e.to_string())?;
+    let sample: Vec<String> = reader
+        .split(b'\n')
+        .take(DETECT_LINES)
+        .filter_map(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).into_owned())
+        .collect();
+    let parser = TimestampParser::detect(&sample)
+        .ok_or_else(|| "could not detect the timestamp format, configure timestamp_format for this service".to_string())?;
+    info!("Detected timestamp format {} for {}", parser.format(), path);
+    Ok(parser)
+}
+
+// 有开始时间且文件未压缩时定位到开始时间附近再顺序扫描：优先使用已有的行索引，
+// 没有索引时二分查找，并在后台为大文件构建索引供后续查询使用
+fn open_at(path: &str, parser: &TimestampParser, start: Option<NaiveDateTime>) -> std::io::Result<(Box<dyn BufRead>, u64)> {
+    let Some(start) = start.filter(|_| !path.ends_with(".gz")) else {
+        return Ok((open_reader(path)?, 0));
+    };
+    let index = line_index::load(path, Some(parser), false).unwrap_or_else(|e| {
+        warn!("Failed to load line index for {}: {}", path, e);
+        None
+    });
+    let mut file = File::open(path)?;
+    let (offset, at_line_start) = match index.as_ref().and_then(|index| index.block_for_time(start)) {
+        Some(block) => (block.offset, true),
+        None => {
+            if index.is_none() && file.metadata()?.len() >= line_index::MIN_INDEXED_SIZE {
+                line_index::build_in_background(path.to_string(), Some(parser.clone()));
+            }
+            (parser.seek_offset(&mut file, start)?, false)
+        }
+    };
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+    let mut skipped = 0;
+    if offset > 0 && !at_line_start {
+        skipped = reader.read_until(b'\n', &mut Vec::new())? as u64; // 跳过不完整的行
+    }
+    Ok((Box::new(reader), skipped))
+}
+
+// 按行读取文件；配置了多行规则时把后续行合并到同一个事件中
+struct EventReader<'a> {
+    reader: Box<dyn BufRead>,
+    rule: Option<&'a MultilineRule>,
+    pending: Option<String>, // 已读出、属于下一个事件的第一行
+    buf: Vec<u8>,
+    bytes_scanned: u64,
+}
+
+impl EventReader<'_> {
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buf.clear();
+        let n = self.reader.read_until(b'\n', &mut self.buf)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        self.bytes_scanned += n as u64;
+        let line = String::from_utf8_lossy(&self.buf);
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    fn next_event(&mut self) -> std::io::Result<Option<Vec<String>>> {
+        let first = match self.pending.take() {
+            Some(line) => line,
+            None => match self.next_line()? {
+                Some(line) => line,
+                None => return Ok(None),
+            },
+        };
+        let mut lines = vec![first];
+        if let Some(rule) = self.rule {
+            while lines.len() < rule.max_lines {
+                if rule.match_before && !rule.joins(lines.last().unwrap()) {
+                    break;
+                }
+                match self.next_line()? {
+                    Some(line) if rule.match_before || rule.joins(&line) => lines.push(line),
+                    Some(line) => {
+                        self.pending = Some(line);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+        Ok(Some(lines))
+    }
+}
+
+// 逐个事件（默认一行一个事件）扫描文件，输出匹配的事件及其前后 context 个事件；
+// 与 grep -C 一样，不相邻的片段之间用 "--" 分隔。多行事件作为整体匹配并完整输出。
+// 指定时间窗口时按事件第一行的时间过滤，没有时间的沿用上一个事件的时间，遇到晚于结束时间的事件即停止扫描。
+// 达到 limits 中的限制或 cancelled 被置位时提前结束，并在 truncated 中返回原因
+fn grep_file_blocking(
+    path: &str,
+    request: &GrepRequest,
+    limits: GrepLimits,
+    cancelled: &AtomicBool,
+) -> Result<GrepOutput, String> {
+    let window = match &request.window {
+        Some(TimeWindow { parser: Some(parser), start, end }) => Some((parser.clone(), *start, *end)),
+        Some(TimeWindow { parser: None, start, end }) => Some((detect_parser(path)?, *start, *end)),
+        None => None,
+    };
+    let (reader, bytes_scanned) = match &window {
+        Some((parser, start, _)) => open_at(path, parser, *start),
+        None => open_reader(path).map(|reader| (reader, 0)),
+    }
+    .map_err(|e| e.to_string())?;
+    let events = EventReader { reader, rule: request.multiline.as_ref(), pending: None, buf: Vec::new(), bytes_scanned };
+    grep_events(events, window, request, limits, cancelled)
+}
+
+type Window = (TimestampParser, Option<NaiveDateTime>, Option<NaiveDateTime>);
+
+fn grep_events(
+    mut events: EventReader,
+    window: Option<Window>,
+    request: &GrepRequest,
+    limits: GrepLimits,
+    cancelled: &AtomicBool,
+) -> Result<GrepOutput, String> {
+    let GrepRequest { filter, context, .. } = request;
+    let context = *context;
+    let deadline = Instant::now() + Duration::from_secs(limits.max_wall_time_secs);
+    let mut output = String::new();
+    let mut before: VecDeque<(usize, String)> = VecDeque::with_capacity(context);
+    let mut after_remaining = 0;
+    let mut last_printed: Option<usize> = None;
+    let mut current_ts: Option<NaiveDateTime> = None;
+    let mut event_no = 0;
+    let mut matched_lines = 0;
+    let mut truncated = None;
+
+    loop {
+        if event_no % CHECK_INTERVAL == 0 {
+            if cancelled.load(Ordering::Relaxed) {
+                truncated = Some(Truncation::Cancelled);
+                break;
+            }
+            if Instant::now() >= deadline {
+                truncated = Some(Truncation::WallTime);
+                break;
+            }
+        }
+        if output.len() >= limits.max_output_bytes {
+            let end = output.as_bytes()[..limits.max_output_bytes].iter().rposition(|&b| b == b'\n');
+            output.truncate(end.map_or(0, |i| i + 1)); // 只保留完整的行
+            truncated = Some(Truncation::OutputBytes);
+            break;
+        }
+
+        let Some(lines) = events.next_event().map_err(|e| e.to_string())? else {
+            break;
+        };
+        event_no += 1;
+
+        if let Some((parser, start, end)) = &window {
+            if let Some(ts) = parser.parse_line(&lines[0]) {
+                current_ts = Some(ts);
+            }
+            if end.is_some_and(|end| current_ts.is_some_and(|ts| ts > end)) {
+                break;
+            }
+            if start.is_some_and(|start| current_ts.is_none_or(|ts| ts < start)) {
+                continue;
+            }
+        }
+        let event = lines.join("\n");
+
+        let mut emit = |no: usize, text: &str, output: &mut String| {
+            if context > 0 && last_printed.is_some_and(|last| no > last + 1) {
+                output.push_str("--\n");
+            }
+            output.push_str(text);
+            output.push('\n');
+            last_printed = Some(no);
+        };
+
+        if filter.matches(&event) {
+            if matched_lines == limits.max_matched_lines {
+                truncated = Some(Truncation::MatchedLines);
+                break;
+            }
+            matched_lines += 1;
+            for (no, text) in before.drain(..) {
+                emit(no, &text, &mut output);
+            }
+            emit(event_no, &event, &mut output);
+            after_remaining = context;
+        } else if after_remaining > 0 {
+            emit(event_no, &event, &mut output);
+            after_remaining -= 1;
+        } else if context > 0 {
+            if before.len() == context {
+                before.pop_front();
+            }
+            before.push_back((event_no, event));
+        }
+    }
+
+    Ok(GrepOutput { text: output, bytes_scanned: events.bytes_scanned, matched_lines, truncated })
+}
+
+pub(crate) async fn grep_file(
+    path: String,
+    request: GrepRequest,
+    limits: GrepLimits,
+    cancelled: Arc<AtomicBool>,
+) -> Result<GrepOutput, String> {
+    tokio::task::spawn_blocking(move || grep_file_blocking(&path, &request, limits, &cancelled))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{self, Write};
+
+    fn write_log(dir: &tempfile::TempDir, lines: &[String]) -> String {
+        let path = dir.path().join("app.log");
+        let mut file = File::create(&path).unwrap();
+        for line in lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+        path.to_str().unwrap().to_string()
+    }
+
+    fn request(pattern: &str) -> GrepRequest {
+        GrepRequest {
+            filter: LineFilter::from_request(&json!({ "filter_strings": [pattern] })).unwrap(),
+            context: 0,
+            window: None,
+            multiline: None,
+        }
+    }
+
+    fn grep(path: &str, limits: GrepLimits, cancelled: bool) -> GrepOutput {
+        grep_file_blocking(path, &request("match"), limits, &AtomicBool::new(cancelled)).unwrap()
+    }
+
+    #[test]
+    fn stops_at_max_matched_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines: Vec<String> = (1..=10).map(|n| format!("match {}", n)).collect();
+        let path = write_log(&dir, &lines);
+
+        let output = grep(&path, GrepLimits { max_matched_lines: 3, ..Default::default() }, false);
+        assert_eq!(output.truncated, Some(Truncation::MatchedLines));
+        assert_eq!(output.matched_lines, 3);
+        assert_eq!(output.text, "match 1\nmatch 2\nmatch 3\n");
+
+        let output = grep(&path, GrepLimits::default(), false);
+        assert_eq!(output.truncated, None);
+        assert_eq!(output.matched_lines, 10);
+        assert_eq!(output.bytes_scanned, fs_len(&path));
+    }
+
+    #[test]
+    fn keeps_only_complete_lines_at_max_output_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines: Vec<String> = (1..=10).map(|n| format!("match-{:03}", n)).collect();
+        let path = write_log(&dir, &lines);
+
+        // 每行 10 字节，超过 25 字节时截断到最后一个完整行
+        let output = grep(&path, GrepLimits { max_output_bytes: 25, ..Default::default() }, false);
+        assert_eq!(output.truncated, Some(Truncation::OutputBytes));
+        assert_eq!(output.text, "match-001\nmatch-002\n");
+        assert_eq!(output.matched_lines, 3);
+    }
+
+    #[test]
+    fn stops_at_wall_time_and_on_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines: Vec<String> = (1..=10).map(|n| format!("match {}", n)).collect();
+        let path = write_log(&dir, &lines);
+
+        let output = grep(&path, GrepLimits { max_wall_time_secs: 0, ..Default::default() }, false);
+        assert_eq!(output.truncated, Some(Truncation::WallTime));
+        assert_eq!((output.text.as_str(), output.matched_lines), ("", 0));
+
+        let output = grep(&path, GrepLimits::default(), true);
+        assert_eq!(output.truncated, Some(Truncation::Cancelled));
+        assert_eq!((output.text.as_str(), output.matched_lines, output.bytes_scanned), ("", 0, 0));
+    }
+
+    // 读到指定字节数后置位取消标志，模拟扫描过程中客户端断开
+    struct CancelAfter {
+        inner: io::Cursor<Vec<u8>>,
+        after: u64,
+        cancelled: Arc<AtomicBool>,
+    }
+
+    impl io::Read for CancelAfter {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.inner.position() >= self.after {
+                self.cancelled.store(true, Ordering::Relaxed);
+            }
+            let limit = (self.after.saturating_sub(self.inner.position()) as usize).clamp(1, buf.len());
+            self.inner.read(&mut buf[..limit])
+        }
+    }
+
+    // 取消每 CHECK_INTERVAL 个事件检查一次，返回此前已输出的部分结果
+    #[test]
+    fn returns_partial_output_when_cancelled_during_scan() {
+        let lines: Vec<String> = (1..=CHECK_INTERVAL * 3).map(|n| format!("match {:05}", n)).collect();
+        let content = lines.iter().map(|line| format!("{}\n", line)).collect::<String>().into_bytes();
+        // 读完前 100 行后取消，下一次检查（第 CHECK_INTERVAL 个事件）时停止
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reader = CancelAfter { inner: io::Cursor::new(content), after: 100 * 12, cancelled: cancelled.clone() };
+        let events = EventReader { reader: Box::new(BufReader::new(reader)), rule: None, pending: None, buf: Vec::new(), bytes_scanned: 0 };
+
+        let output = grep_events(events, None, &request("match"), GrepLimits::default(), &cancelled).unwrap();
+        assert_eq!(output.truncated, Some(Truncation::Cancelled));
+        assert_eq!(output.matched_lines, CHECK_INTERVAL as u64);
+        assert_eq!(output.text, lines[..CHECK_INTERVAL].iter().map(|line| format!("{}\n", line)).collect::<String>());
+    }
+
+    fn multiline(yaml: &str) -> MultilineConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn events(content: &str, config: &MultilineConfig) -> Vec<String> {
+        let rule = config.compile().unwrap();
+        let reader: Box<dyn BufRead> = Box::new(io::Cursor::new(content.as_bytes().to_vec()));
+        let mut events = EventReader { reader, rule: Some(&rule), pending: None, buf: Vec::new(), bytes_scanned: 0 };
+        let mut out = Vec::new();
+        while let Some(lines) = events.next_event().unwrap() {
+            out.push(lines.join("|"));
+        }
+        out
+    }
+
+    #[test]
+    fn joins_multiline_events_after_the_matching_line() {
+        let content = "2025-01-02 ERROR failed\n  at a\n  at b\n2025-01-02 INFO ok\n  at c\n";
+        // negate: true, match: after
+        let start = multiline("start_pattern: '^\\d{4}-'");
+        assert_eq!(events(content, &start), vec!["2025-01-02 ERROR failed|  at a|  at b", "2025-01-02 INFO ok|  at c"]);
+        // negate: false, match: after
+        let continuation = multiline("continuation_pattern: '^\\s+at '");
+        assert_eq!(events(content, &continuation), events(content, &start));
+        // 开头的续行自成一个事件
+        assert_eq!(events("  at x\n2025-01-02 INFO ok\n", &start), vec!["  at x", "2025-01-02 INFO ok"]);
+    }
+
+    #[test]
+    fn joins_multiline_events_before_the_matching_line() {
+        // negate: false, match: before
+        let joins_next = multiline("joins_next_pattern: '\\\\$'");
+        assert_eq!(
+            events("SELECT * \\\nFROM t \\\nWHERE x\nsingle\n", &joins_next),
+            vec!["SELECT * \\|FROM t \\|WHERE x", "single"]
+        );
+        // negate: true, match: before
+        let end = multiline("end_pattern: ';$'");
+        assert_eq!(events("BEGIN\nUPDATE t;\nCOMMIT;\ntrailing\n", &end), vec!["BEGIN|UPDATE t;", "COMMIT;", "trailing"]);
+    }
+
+    #[test]
+    fn limits_multiline_events_to_max_lines() {
+        let start = multiline("{ start_pattern: '^E', max_lines: 2 }");
+        assert_eq!(events("E1\na\nb\nE2\n", &start), vec!["E1|a", "b", "E2"]);
+        let end = multiline("{ end_pattern: ';$', max_lines: 2 }");
+        assert_eq!(events("a\nb\nc;\n", &end), vec!["a|b", "c;"]);
+
+        assert!(multiline("{ start_pattern: a, end_pattern: b }").compile().is_err());
+        assert!(multiline("max_lines: 10").compile().is_err());
+    }
+
+    fn fs_len(path: &str) -> u64 {
+        std::fs::metadata(path).unwrap().len()
+    }
+}
@@ -0,0 +1,102 @@
+use crate::system_cmd;
+use async_std::net::TcpStream;
+use async_tungstenite::{client_async, tungstenite::Message};
+use futures::prelude::*;
+use serde_json::{json, Value};
+use std::{env, fs, time::Duration};
+
+// 健康检查：配置加载状态、Filebeat 运行状态，以及日志目录和 Filebeat 输入配置的读写权限。
+// Filebeat 输入配置只读（如 docker-compose 中以 :ro 挂载）时只影响 firebase_upload，作为 warnings 返回，不影响 status
+
+// 日志目录可读，返回目录下文件数
+fn check_log_dir(path: &str) -> Value {
+    match fs::read_dir(path) {
+        Ok(entries) => json!({ "path": path, "readable": true, "files": entries.count() }),
+        Err(e) => json!({ "path": path, "readable": false, "error": e.to_string() }),
+    }
+}
+
+// firebase_upload 需要改写 Filebeat 输入配置；以追加方式打开可以检测只读挂载，且不会修改文件
+fn check_writable(path: &str) -> Value {
+    match fs::OpenOptions::new().append(true).open(path) {
+        Ok(_) => json!({ "path": path, "writable": true }),
+        Err(e) => json!({ "path": path, "writable": false, "error": e.to_string() }),
+    }
+}
+
+pub(crate) async fn health_report(config_path: &str, config_error: Option<&str>, log_dirs: &[String]) -> Value {
+    let filebeat_inputs_path = env::var("FILEBEAT_CONFIG_LOG_PATH")
+        .unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/filebeat_restful/filebeat/inputs.d/log.yml".to_string());
+
+    // 与 firebase_upload 一致：设置了 FILEBEAT_CONFIG_MAIN_PATH 时 Filebeat 与本服务运行在同一容器内
+    let (mode, (running, detail)) = if env::var("FILEBEAT_CONFIG_MAIN_PATH").is_ok() {
+        ("process", system_cmd::filebeat_process_status())
+    } else {
+        ("docker", system_cmd::filebeat_container_status("filebeat").await)
+    };
+
+    let dirs: Vec<Value> = log_dirs.iter().map(|dir| check_log_dir(dir)).collect();
+    let inputs = check_writable(&filebeat_inputs_path);
+
+    let healthy = config_error.is_none() && running && dirs.iter().all(|d| d["readable"] == true);
+    let mut warnings = Vec::new();
+    if inputs["writable"] != true {
+        warnings.push(format!("Filebeat inputs config {} is not writable, firebase_upload will fail", filebeat_inputs_path));
+    }
+
+    json!({
+        "cmd": "health",
+        "status": if healthy { "ok" } else { "degraded" },
+        "warnings": warnings,
+        "config": {
+            "path": config_path,
+            "loaded": config_error.is_none(),
+            "error": config_error,
+        },
+        "filebeat": {
+            "mode": mode,
+            "running": running,
+            "detail": detail,
+        },
+        "disk": {
+            "log_dirs": dirs,
+            "filebeat_inputs": inputs,
+        }
+    })
+}
+
+// `filebeat_restful healthcheck`：连接本机服务发送 health 命令，供 docker/k8s 探针使用，健康时退出码为 0
+pub async fn run_healthcheck(addr: &str) -> i32 {
+    let check = async {
+        let stream = TcpStream::connect(addr).await.map_err(|e| e.to_string())?;
+        let (mut ws_stream, _) = client_async(format!("ws://{}", addr), stream)
+            .await
+            .map_err(|e| e.to_string())?;
+        ws_stream
+            .send(Message::Text("health".to_string()))
+            .await
+            .map_err(|e| e.to_string())?;
+        match ws_stream.next().await {
+            Some(Ok(Message::Text(text))) => Ok(text),
+            Some(Ok(other)) => Err(format!("Unexpected message: {:?}", other)),
+            Some(Err(e)) => Err(e.to_string()),
+            None => Err("Connection closed".to_string()),
+        }
+    };
+
+    match tokio::time::timeout(Duration::from_secs(10), check).await {
+        Ok(Ok(text)) => {
+            println!("{}", text);
+            let report: Value = serde_json::from_str(&text).unwrap_or_default();
+            if report["status"] == "ok" { 0 } else { 1 }
+        }
+        Ok(Err(e)) => {
+            eprintln!("healthcheck failed: {}", e);
+            1
+        }
+        Err(_) => {
+            eprintln!("healthcheck timed out");
+            1
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Instant, UNIX_EPOCH};
+use std::{env, thread};
+use crate::grep::detect_parser;
+use crate::timestamp::TimestampParser;
+
+// 大文件的稀疏行索引：每隔约 BLOCK_BYTES 字节记录一个块（起始行号、字节偏移、块内第一条/最后一条日志时间），
+// 用于 file_read 跳转到指定行、file_grep 按时间窗口定位起点。
+// 索引缓存在内存中并持久化到 LINE_INDEX_DIR；文件大小、修改时间或 inode 变化时失效，
+// 同一 inode 且只是追加写入时从最后一个块开始增量更新。copytruncate 轮转后文件可能很快又增长到原来的大小，
+// 因此增量更新前先比较已索引部分开头和末尾的内容指纹，不一致时重新构建。只索引未压缩的文件。
+
+const INDEX_VERSION: u32 = 2;
+const BLOCK_BYTES: u64 = 1024 * 1024;
+const FINGERPRINT_BYTES: u64 = 4096;
+pub(crate) const MIN_INDEXED_SIZE: u64 = 4 * 1024 * 1024; // 小于该大小的文件直接顺序扫描
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+struct FileKey {
+    size: u64,
+    mtime_ms: u64,
+    inode: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Block {
+    pub(crate) line: u64,   // 块内第一行的行号（从 1 开始）
+    pub(crate) offset: u64, // 块内第一行的字节偏移
+    first_ts: Option<NaiveDateTime>,
+    last_ts: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct LineIndex {
+    version: u32,
+    path: String,
+    key: FileKey,
+    timestamp_format: Option<String>,
+    pub(crate) total_lines: u64, // 已索引的完整行数，末尾未写完的行不计入
+    indexed_size: u64,
+    fingerprint: u64, // 已索引部分开头和末尾 FINGERPRINT_BYTES 字节的哈希
+    blocks: Vec<Block>,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<String, Arc<LineIndex>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static BUILDING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn index_dir() -> PathBuf {
+    env::var("LINE_INDEX_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir().join("filebeat_restful_index"))
+}
+
+fn index_file(path: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    index_dir().join(format!("{:016x}.json", hasher.finish()))
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+fn file_key(path: &str) -> io::Result<FileKey> {
+    let metadata = fs::metadata(path)?;
+    let mtime_ms = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    Ok(FileKey { size: metadata.len(), mtime_ms, inode: inode(&metadata) })
+}
+
+// 文件 [0, size) 范围内开头和末尾各 FINGERPRINT_BYTES 字节的哈希
+fn fingerprint(path: &str, size: u64) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = Vec::new();
+    for start in [0, size.saturating_sub(FINGERPRINT_BYTES)] {
+        buf.clear();
+        file.seek(SeekFrom::Start(start))?;
+        (&mut file).take(FINGERPRINT_BYTES.min(size)).read_to_end(&mut buf)?;
+        buf.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+impl LineIndex {
+    // 从 blocks 的最后一个块（没有块时从文件开头）继续扫描到文件末尾的最后一个完整行
+    fn scan(&mut self, parser: Option<&TimestampParser>) -> io::Result<()> {
+        let (mut line, mut offset) = match self.blocks.pop() {
+            Some(block) => (block.line, block.offset),
+            None => (1, 0),
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            if n == 0 || buf.last() != Some(&b'\n') {
+                break;
+            }
+            if self.blocks.last().is_none_or(|block| offset - block.offset >= BLOCK_BYTES) {
+                self.blocks.push(Block { line, offset, first_ts: None, last_ts: None });
+            }
+            if let Some(ts) = parser.and_then(|parser| parser.parse_line(&String::from_utf8_lossy(&buf))) {
+                let block = self.blocks.last_mut().unwrap();
+                block.first_ts.get_or_insert(ts);
+                block.last_ts = Some(ts);
+            }
+            line += 1;
+            offset += n as u64;
+        }
+        self.total_lines = line - 1;
+        self.indexed_size = offset;
+        self.fingerprint = fingerprint(&self.path, offset)?;
+        Ok(())
+    }
+
+    // 已索引部分的内容没有被改写（只是在末尾追加）
+    fn prefix_unchanged(&self) -> bool {
+        fingerprint(&self.path, self.indexed_size).is_ok_and(|fingerprint| fingerprint == self.fingerprint)
+    }
+
+    fn build(path: &str, key: FileKey, parser: Option<&TimestampParser>) -> io::Result<LineIndex> {
+        let mut index = LineIndex {
+            version: INDEX_VERSION,
+            path: path.to_string(),
+            key,
+            timestamp_format: parser.map(|parser| parser.format().to_string()),
+            total_lines: 0,
+            indexed_size: 0,
+            fingerprint: 0,
+            blocks: Vec::new(),
+        };
+        index.scan(parser)?;
+        Ok(index)
+    }
+
+    pub(crate) fn timestamp_format(&self) -> Option<&str> {
+        self.timestamp_format.as_deref()
+    }
+
+    // 不晚于 line 的最后一个块
+    pub(crate) fn block_for_line(&self, line: u64) -> Option<&Block> {
+        let i = self.blocks.partition_point(|block| block.line <= line);
+        i.checked_sub(1).map(|i| &self.blocks[i])
+    }
+
+    pub(crate) fn block_for_offset(&self, offset: u64) -> Option<&Block> {
+        let i = self.blocks.partition_point(|block| block.offset <= offset);
+        i.checked_sub(1).map(|i| &self.blocks[i])
+    }
+
+    // 第一个可能包含 start 之后日志的块；块内没有时间的视为可能包含
+    pub(crate) fn block_for_time(&self, start: NaiveDateTime) -> Option<&Block> {
+        self.blocks.iter().find(|block| block.last_ts.is_none_or(|ts| ts >= start))
+    }
+
+    // 统计 offset 所在行的行号；offset 超出已索引范围时返回 None
+    pub(crate) fn line_at(&self, offset: u64) -> io::Result<Option<u64>> {
+        let Some(block) = self.block_for_offset(offset).filter(|_| offset <= self.indexed_size) else {
+            return Ok(None);
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(block.offset))?;
+        let mut newlines = 0;
+        let mut reader = BufReader::new(file).take(offset - block.offset);
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            newlines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+        }
+        Ok(Some(block.line + newlines))
+    }
+}
+
+fn load_from_disk(path: &str) -> Option<LineIndex> {
+    let content = fs::read(index_file(path)).ok()?;
+    serde_json::from_slice::<LineIndex>(&content)
+        .ok()
+        .filter(|index| index.version == INDEX_VERSION && index.path == path)
+}
+
+fn save_to_disk(index: &LineIndex) {
+    let target = index_file(&index.path);
+    let tmp = target.with_extension("tmp");
+    let result = fs::create_dir_all(index_dir())
+        .and_then(|_| fs::write(&tmp, serde_json::to_vec(index).map_err(io::Error::other)?))
+        .and_then(|_| fs::rename(&tmp, &target));
+    if let Err(e) = result {
+        warn!("Failed to save line index for {} to {:?}: {}", index.path, target, e);
+    }
+}
+
+// 返回与当前文件一致的索引：内存或磁盘中的索引有效时直接使用，追加写入时增量更新；
+// build 为 false 时不从头构建。parser 与索引记录的时间格式不一致时视为失效。
+pub(crate) fn load(path: &str, parser: Option<&TimestampParser>, build: bool) -> io::Result<Option<Arc<LineIndex>>> {
+    let key = file_key(path)?;
+    if path.ends_with(".gz") || key.size < MIN_INDEXED_SIZE {
+        return Ok(None);
+    }
+    let format = parser.map(|parser| parser.format());
+    let usable = |index: &LineIndex| format.is_none() || index.timestamp_format() == format;
+    let in_memory = CACHE.lock().unwrap().get(path).cloned();
+    if let Some(index) = in_memory.as_ref().filter(|index| index.key == key && usable(index)) {
+        return Ok(Some(index.clone()));
+    }
+    let cached = in_memory
+        .map(|index| (*index).clone())
+        .or_else(|| load_from_disk(path))
+        .filter(|index| usable(index));
+
+    let index = match cached {
+        Some(index) if index.key == key => return Ok(Some(remember(index, false))),
+        Some(mut index)
+            if index.key.inode == key.inode && key.size >= index.indexed_size && index.prefix_unchanged() =>
+        {
+            let stored = index.timestamp_format().and_then(|format| TimestampParser::new(format).ok());
+            index.key = key;
+            index.scan(parser.or(stored.as_ref()))?;
+            index
+        }
+        _ if build => {
+            let started = Instant::now();
+            let detected = parser.is_none().then(|| detect_parser(path).ok()).flatten();
+            let index = LineIndex::build(path, key, parser.or(detected.as_ref()))?;
+            info!(
+                "Built line index for {}: {} lines, {} blocks in {:?}",
+                path,
+                index.total_lines,
+                index.blocks.len(),
+                started.elapsed()
+            );
+            index
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(remember(index, true)))
+}
+
+fn remember(index: LineIndex, changed: bool) -> Arc<LineIndex> {
+    if changed {
+        save_to_disk(&index);
+    }
+    let index = Arc::new(index);
+    CACHE.lock().unwrap().insert(index.path.clone(), index.clone());
+    index
+}
+
+// 在后台线程中构建索引，同一文件同时只构建一次
+pub(crate) fn build_in_background(path: String, parser: Option<TimestampParser>) {
+    if !BUILDING.lock().unwrap().insert(path.clone()) {
+        return;
+    }
+    thread::spawn(move || {
+        if let Err(e) = load(&path, parser.as_ref(), true) {
+            warn!("Failed to build line index for {}: {}", path, e);
+        }
+        BUILDING.lock().unwrap().remove(&path);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    fn time(second: u64) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-01-01 00:00:00", FORMAT).unwrap() + chrono::Duration::seconds(second as i64)
+    }
+
+    // 第 n 行（从 1 开始）的时间为 n 秒，每行约 100 字节
+    fn write_lines(file: &mut File, from: u64, count: u64) {
+        let mut writer = io::BufWriter::new(file);
+        for n in from..from + count {
+            writeln!(writer, "{} INFO line {:08} {}", time(n).format(FORMAT), n, "x".repeat(70)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    fn parser() -> TimestampParser {
+        TimestampParser::new(FORMAT).unwrap()
+    }
+
+    fn index_of(path: &str) -> Arc<LineIndex> {
+        load(path, Some(&parser()), true).unwrap().expect("file is large enough to be indexed")
+    }
+
+    #[test]
+    fn builds_and_locates_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        write_lines(&mut File::create(&path).unwrap(), 1, 50_000);
+        let path = path.to_str().unwrap();
+
+        let index = index_of(path);
+        assert_eq!(index.total_lines, 50_000);
+        assert_eq!(index.indexed_size, fs::metadata(path).unwrap().len());
+        assert!(index.blocks.len() > 1);
+        assert_eq!(index.timestamp_format(), Some(FORMAT));
+
+        let block = index.block_for_line(30_000).unwrap();
+        assert!(block.line <= 30_000 && 30_000 - block.line < BLOCK_BYTES / 100);
+        assert_eq!(index.block_for_line(1).unwrap().offset, 0);
+
+        let block = index.block_for_time(time(40_000)).unwrap();
+        assert!(block.first_ts.unwrap() <= time(40_000) && block.last_ts.unwrap() >= time(40_000));
+        assert_eq!(index.block_for_time(time(0)).unwrap().line, 1);
+        assert!(index.block_for_time(time(60_000)).is_none());
+
+        // line_at 与逐行统计一致
+        let content = fs::read(path).unwrap();
+        let offset = 2_500_000;
+        let expected = 1 + content[..offset].iter().filter(|&&b| b == b'\n').count() as u64;
+        assert_eq!(index.line_at(offset as u64).unwrap(), Some(expected));
+        assert_eq!(index.line_at(0).unwrap(), Some(1));
+        assert_eq!(index.line_at(index.indexed_size + 1).unwrap(), None);
+    }
+
+    #[test]
+    fn updates_incrementally_on_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        write_lines(&mut File::create(&path).unwrap(), 1, 50_000);
+        let path_str = path.to_str().unwrap();
+        let first = index_of(path_str);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write_lines(&mut file, 50_001, 1_000);
+        // 末尾未写完的行不计入
+        file.write_all(b"2025-01-01 partial").unwrap();
+
+        let index = index_of(path_str);
+        assert_eq!(index.total_lines, 51_000);
+        assert_eq!(index.indexed_size, fs::metadata(&path).unwrap().len() - 18);
+        // 之前的块保持不变，只从最后一个块开始重新扫描
+        let kept = first.blocks.len() - 1;
+        assert_eq!(
+            index.blocks[..kept].iter().map(|block| block.offset).collect::<Vec<_>>(),
+            first.blocks[..kept].iter().map(|block| block.offset).collect::<Vec<_>>()
+        );
+        assert_eq!(index.block_for_time(time(50_500)).unwrap().last_ts, Some(time(51_000)));
+    }
+
+    #[test]
+    fn rebuilds_after_truncate_and_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        write_lines(&mut File::create(&path).unwrap(), 1, 50_000);
+        let path_str = path.to_str().unwrap();
+        let first = index_of(path_str);
+
+        // copytruncate：同一 inode 截断后写入更多的新内容，行号和时间都从头开始
+        let mut file = fs::OpenOptions::new().write(true).truncate(true).open(&path).unwrap();
+        write_lines(&mut file, 100_001, 60_000);
+        drop(file);
+        assert_eq!(file_key(path_str).unwrap().inode, first.key.inode);
+
+        let index = index_of(path_str);
+        assert_eq!(index.total_lines, 60_000);
+        assert_eq!(index.blocks[0].first_ts, Some(time(100_001)));
+        assert!(index.block_for_time(time(100_000)).is_some_and(|block| block.line == 1));
+    }
+}
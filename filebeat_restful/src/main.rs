@@ -1,15 +1,32 @@
 pub mod websocket;
 mod modify_filebeat_yaml;
 mod system_cmd;
+mod health;
+mod metrics;
+mod grep;
+mod timestamp;
+mod file_read;
+mod line_index;
+mod download;
 
 use websocket::{WebSocketServer};
-use env_logger;
 use env_logger::Env;
 
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+    // 健康检查模式：供 docker-compose/k8s 探针调用
+    if std::env::args().nth(1).as_deref() == Some("healthcheck") {
+        std::process::exit(health::run_healthcheck("127.0.0.1:9002").await);
+    }
+
+    // 启动指标导出服务
+    let metrics_addr = std::env::var("METRICS_ADDR").unwrap_or_else(|_| "0.0.0.0:9102".to_string());
+    tokio::spawn(async move {
+        metrics::serve(&metrics_addr).await;
+    });
+
     // 启动 WebSocket 服务器
     let mut ws = WebSocketServer::new();
 
@@ -0,0 +1,107 @@
+use log::*;
+use prometheus::{
+    register_int_counter, register_int_counter_vec, register_int_gauge, Encoder, IntCounter, IntCounterVec,
+    IntGauge, TextEncoder,
+};
+use std::sync::LazyLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Prometheus 指标，通过独立的 HTTP 端口（METRICS_ADDR，默认 0.0.0.0:9102）以文本格式导出
+
+pub static ACTIVE_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("edge_active_connections", "Open WebSocket connections")
+        .expect("register edge_active_connections")
+});
+
+pub static COMMANDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("edge_commands_total", "Received commands by type", &["cmd"])
+        .expect("register edge_commands_total")
+});
+
+pub static GREP_BYTES_SCANNED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("edge_grep_bytes_scanned_total", "Bytes of log files scanned by file_grep")
+        .expect("register edge_grep_bytes_scanned_total")
+});
+
+pub static GREP_TRUNCATED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("edge_grep_truncated_total", "file_grep scans stopped early by reason", &["reason"])
+        .expect("register edge_grep_truncated_total")
+});
+
+pub static DOWNLOAD_BYTES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("edge_download_bytes_total", "Bytes sent by file_download")
+        .expect("register edge_download_bytes_total")
+});
+
+pub static UPLOAD_JOBS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("edge_upload_jobs_total", "firebase_upload jobs by result", &["result"])
+        .expect("register edge_upload_jobs_total")
+});
+
+pub static FILEBEAT_RESTARTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("edge_filebeat_restarts_total", "Filebeat (re)starts by result", &["result"])
+        .expect("register edge_filebeat_restarts_total")
+});
+
+pub fn result_label<T, E>(result: &Result<T, E>) -> &'static str {
+    if result.is_ok() { "success" } else { "failure" }
+}
+
+fn render() -> Result<Vec<u8>, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
+
+// 只处理 GET /metrics 的最小 HTTP 响应
+async fn handle_request(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request).lines().next().unwrap_or_default().to_string();
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next().map(|p| p.split('?').next().unwrap_or(p))) {
+        (Some("GET"), Some("/metrics")) => match render() {
+            Ok(body) => ("200 OK", prometheus::TEXT_FORMAT, body),
+            Err(e) => ("500 Internal Server Error", "text/plain", e.to_string().into_bytes()),
+        },
+        _ => ("404 Not Found", "text/plain", b"not found\n".to_vec()),
+    };
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+pub async fn serve(addr: &str) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Can't listen on {} for metrics: {}", addr, e);
+            return;
+        }
+    };
+    info!("Metrics service is listening on: {}", addr);
+
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(stream).await {
+                info!("Error serving metrics: {}", e);
+            }
+        });
+    }
+}
@@ -1,58 +1,59 @@
-use serde::{Deserialize, Serialize};
-use serde_yaml::{Value, from_reader, to_writer, to_string, Serializer, Sequence};
+use serde_yaml::{Mapping, Value, from_reader, to_writer};
 use std::fs::File;
+use crate::grep::MultilineConfig;
 
-// 定义结构体来对应 YAML 文件中的数据结构
-#[derive(Debug, Serialize, Deserialize)]
-struct LogConfig {
-    r#type: String,
-    enabled: bool,
-    paths: Vec<String>,
-    fields: Fields,
-    scan_frequency: String,
-    close_inactive: String,
-    fields_under_root: bool,
+// Filebeat 的 multiline 设置，negate / match 由配置的规则种类决定（见 MultilineConfig）
+fn multiline_settings(multiline: &MultilineConfig) -> Value {
+    let (pattern, negate, match_before) = multiline.pattern().unwrap_or(("", false, false));
+    let mut settings = Mapping::new();
+    settings.insert("type".into(), "pattern".into());
+    settings.insert("pattern".into(), pattern.into());
+    settings.insert("negate".into(), negate.into());
+    settings.insert("match".into(), if match_before { "before" } else { "after" }.into());
+    settings.insert("max_lines".into(), (multiline.max_lines as u64).into());
+    Value::Mapping(settings)
 }
 
-// 结构体用于嵌套字段 "fields"
-#[derive(Debug, Serialize, Deserialize)]
-struct Fields {
-    service: String,
-    hostname: String,
-}
-
-// 修改 YAML 文件中的 paths, service 和 hostname 字段
-pub fn modify_yaml(file_path: &str, new_paths: Vec<String>, new_service: String, new_hostname: String) -> Result<(), Box<dyn std::error::Error>> {
-    // 打开文件并解析 YAML 内容
-    let file = File::open(file_path)?;
-    let mut data: Vec<LogConfig> = serde_yaml::from_reader(file)?;
-
-    // 假设我们修改第一个配置项的 `paths`, `service` 和 `hostname`
-    if let Some(config) = data.get_mut(0) {
-        // 修改 `paths` 字段
-        config.paths = new_paths;
-        // 修改 `service` 字段
-        config.fields.service = new_service;
-        // 修改 `hostname` 字段
-        config.fields.hostname = new_hostname;
+// filestream 输入的 multiline 写在 parsers 中，log 输入直接写在 multiline 下；
+// 服务未配置多行规则时移除已有的 multiline 设置
+fn apply_multiline(config: &mut Value, multiline: Option<&MultilineConfig>) {
+    let Some(input) = config.as_mapping_mut() else {
+        return;
+    };
+    if input.get("type").and_then(Value::as_str) == Some("log") {
+        match multiline {
+            Some(multiline) => {
+                input.insert("multiline".into(), multiline_settings(multiline));
+            }
+            None => {
+                input.remove("multiline");
+            }
+        }
+        return;
     }
 
-    // 打开文件进行写回修改后的内容
-    let file = File::create(file_path)?;
-
-    // 序列化数据为字符串，并自定义缩进
-    let yaml_string = to_string(&data)?;
-
-    // 自定义缩进（例如：每个缩进为 4 个空格）
-    let yaml_string_with_custom_indent = yaml_string.replace("  -", "\n    -"); // 可以根据需要进一步处理
-
-    serde_yaml::to_writer(file, &yaml_string_with_custom_indent.as_bytes())?;
-
-    Ok(())
+    let mut parsers: Vec<Value> = match input.remove("parsers") {
+        Some(Value::Sequence(parsers)) => parsers.into_iter().filter(|parser| parser.get("multiline").is_none()).collect(),
+        _ => Vec::new(),
+    };
+    if let Some(multiline) = multiline {
+        let mut parser = Mapping::new();
+        parser.insert("multiline".into(), multiline_settings(multiline));
+        parsers.push(Value::Mapping(parser));
+    }
+    if !parsers.is_empty() {
+        input.insert("parsers".into(), Value::Sequence(parsers));
+    }
 }
 
 // 使用 `serde_yaml::Value` 进行修改（动态结构）
-pub fn modify_yaml_dynamic(file_path: &str, new_paths: Vec<String>, new_service: String, new_hostname: String) -> Result<(), Box<dyn std::error::Error>> {
+pub fn modify_yaml_dynamic(
+    file_path: &str,
+    new_paths: Vec<String>,
+    new_service: String,
+    new_hostname: String,
+    multiline: Option<&MultilineConfig>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let file = File::open(file_path)?;
     let mut data: Value = from_reader(file)?;
 
@@ -68,6 +69,7 @@ pub fn modify_yaml_dynamic(file_path: &str, new_paths: Vec<String>, new_service:
                 *hostname = Value::String(new_hostname);
             }
         }
+        apply_multiline(config, multiline);
     }
 
     // 写回修改后的数据
@@ -77,3 +79,53 @@ pub fn modify_yaml_dynamic(file_path: &str, new_paths: Vec<String>, new_service:
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multiline(yaml: &str) -> MultilineConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn input(yaml: &str) -> Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn writes_multiline_for_log_inputs() {
+        let mut config = input("{ type: log, paths: [/var/log/app.log] }");
+        apply_multiline(&mut config, Some(&multiline("start_pattern: '^\\d{4}-'")));
+        assert_eq!(
+            config["multiline"],
+            input("{ type: pattern, pattern: '^\\d{4}-', negate: true, match: after, max_lines: 500 }")
+        );
+        assert!(config.get("parsers").is_none());
+
+        apply_multiline(&mut config, Some(&multiline("{ joins_next_pattern: '\\\\$', max_lines: 20 }")));
+        assert_eq!(config["multiline"], input("{ type: pattern, pattern: '\\\\$', negate: false, match: before, max_lines: 20 }"));
+
+        apply_multiline(&mut config, None);
+        assert_eq!(config, input("{ type: log, paths: [/var/log/app.log] }"));
+    }
+
+    #[test]
+    fn writes_multiline_parser_for_filestream_inputs() {
+        // 替换已有的 multiline parser，保留其他 parser
+        let mut config = input(
+            "{ type: filestream, parsers: [ { ndjson: { target: '' } }, { multiline: { type: pattern, pattern: old } } ] }",
+        );
+        apply_multiline(&mut config, Some(&multiline("end_pattern: ';$'")));
+        assert_eq!(
+            config["parsers"],
+            input("[ { ndjson: { target: '' } }, { multiline: { type: pattern, pattern: ';$', negate: true, match: before, max_lines: 500 } } ]")
+        );
+        assert!(config.get("multiline").is_none());
+
+        apply_multiline(&mut config, None);
+        assert_eq!(config["parsers"], input("[ { ndjson: { target: '' } } ]"));
+
+        let mut config = input("{ type: filestream, parsers: [ { multiline: { pattern: old } } ] }");
+        apply_multiline(&mut config, None);
+        assert_eq!(config, input("{ type: filestream }"));
+    }
+}
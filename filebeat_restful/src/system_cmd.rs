@@ -1,8 +1,6 @@
-use std::str;
+use std::{fs, str};
 use log::info;
-use tokio::io::{self, AsyncWriteExt};
 use tokio::process::{Command};
-use std::process::Stdio; // 从标准库导入 Stdio
 
 pub(crate) async fn start_filebeat(filebeat_config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let filebeat_output = Command::new("/usr/share/filebeat/filebeat")
@@ -66,27 +64,46 @@ pub(crate) async fn get_and_restart_container(service_name: &str) -> Result<(),
 }
 
 
-pub(crate) async fn grep_multiple_layers(path: &str, patterns: Vec<String>, context_line: i64) -> Result<String, Box<dyn std::error::Error>> {
-    let mut command = String::new();
-
-    // 拼接第一个 grep 命令
-    if  { context_line <= 0 } {
-        command.push_str(&format!("grep -a {} {}", patterns[0], path));
-    } else {
-        command.push_str(&format!("grep -C {} -a {} {}", context_line, patterns[0], path));
-    }
-
-    // 拼接后续的 grep 命令
-    for pattern in patterns.iter().skip(1) {
-        command.push_str(&format!(" | grep -a {}", pattern));
+// 检查 Filebeat 进程是否在运行（与本服务运行在同一容器内时），返回 (是否运行, 说明)
+pub(crate) fn filebeat_process_status() -> (bool, String) {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(e) => return (false, format!("Failed to read /proc: {}", e)),
+    };
+    for entry in entries.flatten() {
+        let pid = entry.file_name().to_string_lossy().to_string();
+        if !pid.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let cmdline = fs::read(entry.path().join("cmdline")).unwrap_or_default();
+        let program = cmdline.split(|b| *b == 0).next().unwrap_or_default();
+        if String::from_utf8_lossy(program).ends_with("filebeat") {
+            return (true, format!("filebeat running with pid {}", pid));
+        }
     }
+    (false, "filebeat process not found".to_string())
+}
 
-    let output = Command::new("bash")
-        .arg("-c")
-        .arg(command) // 使用 bash 执行拼接的命令字符串
+// 检查 Filebeat 容器是否在运行（通过 docker 管理 Filebeat 时）
+pub(crate) async fn filebeat_container_status(service_name: &str) -> (bool, String) {
+    let output = Command::new("docker")
+        .arg("ps")
+        .arg("--filter")
+        .arg(format!("name={}", service_name))
+        .arg("--format")
+        .arg("{{.Names}} {{.Status}}")
         .output()
-        .await?;
-
-    let result = String::from_utf8_lossy(&output.stdout).to_string();
-    Ok(result)
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if status.is_empty() {
+                (false, format!("no running container matches '{}'", service_name))
+            } else {
+                (true, status)
+            }
+        }
+        Ok(output) => (false, String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => (false, format!("Failed to execute command: {}", e)),
+    }
 }
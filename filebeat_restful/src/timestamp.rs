@@ -0,0 +1,281 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Offset};
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+
+// 日志行时间解析：按 ServiceType 配置的 timestamp_format（chrono 格式）解析，
+// 未配置时根据文件开头的若干行在常见格式中自动识别
+
+const KNOWN_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%m/%d/%y %H:%M:%S%.f",
+    "%d/%b/%Y:%H:%M:%S",
+    "%Y%m%d %H:%M:%S%.f",
+];
+const REQUEST_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"];
+
+pub(crate) const DETECT_LINES: usize = 50;
+const SCAN_PREFIX: usize = 256;              // 只在每行前 N 个字节中查找时间
+const SEEK_GRANULARITY: u64 = 64 * 1024;     // 二分查找到该精度后改为顺序扫描
+const PROBE_BYTES: usize = 64 * 1024;        // 每次探测最多读取的字节数
+
+#[derive(Clone)]
+pub(crate) struct TimestampParser {
+    format: String,
+    regex: Regex,
+}
+
+// 将 chrono 格式转换为用于在行中定位时间的正则
+fn format_to_regex(format: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push_str(&regex::escape(&c.to_string()));
+            continue;
+        }
+        let piece = match chars.next() {
+            Some('Y') => r"\d{4}",
+            Some('y' | 'm' | 'd' | 'H' | 'M' | 'S' | 'I') => r"\d{2}",
+            Some('e') => r"[ \d]\d",
+            Some('j') => r"\d{3}",
+            Some('b' | 'h') => r"[A-Za-z]{3}",
+            Some('B') => r"[A-Za-z]+",
+            Some('p') => r"[AaPp][Mm]",
+            Some('f') => r"\d+",
+            Some('T') => r"\d{2}:\d{2}:\d{2}",
+            Some('F') => r"\d{4}-\d{2}-\d{2}",
+            Some('%') => "%",
+            Some('.') => match chars.next() {
+                Some('f') => r"(?:\.\d+)?",
+                Some('3') if chars.next() == Some('f') => r"\.\d{3}",
+                Some('6') if chars.next() == Some('f') => r"\.\d{6}",
+                Some('9') if chars.next() == Some('f') => r"\.\d{9}",
+                _ => return Err(format!("unsupported fraction specifier in '{}'", format)),
+            },
+            Some(other) => return Err(format!("unsupported specifier %{} in '{}'", other, format)),
+            None => return Err(format!("dangling % in '{}'", format)),
+        };
+        out.push_str(piece);
+    }
+    Ok(out)
+}
+
+fn line_prefix(line: &str) -> &str {
+    if line.len() <= SCAN_PREFIX {
+        return line;
+    }
+    let mut end = SCAN_PREFIX;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+impl TimestampParser {
+    pub(crate) fn new(format: &str) -> Result<TimestampParser, String> {
+        let regex = Regex::new(&format_to_regex(format)?).map_err(|e| e.to_string())?;
+        Ok(TimestampParser { format: format.to_string(), regex })
+    }
+
+    pub(crate) fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub(crate) fn parse_line(&self, line: &str) -> Option<NaiveDateTime> {
+        let found = self.regex.find(line_prefix(line))?;
+        NaiveDateTime::parse_from_str(found.as_str(), &self.format).ok()
+    }
+
+    // 选择能解析最多样本行的常见格式
+    pub(crate) fn detect(lines: &[String]) -> Option<TimestampParser> {
+        KNOWN_FORMATS
+            .iter()
+            .filter_map(|format| TimestampParser::new(format).ok())
+            .map(|parser| {
+                let parsed = lines.iter().filter(|line| parser.parse_line(line).is_some()).count();
+                (parsed, parser)
+            })
+            .filter(|(parsed, _)| *parsed > 0)
+            .max_by_key(|(parsed, _)| *parsed)
+            .map(|(_, parser)| parser)
+    }
+
+    // 从 offset 之后的第一个完整行开始，返回找到的第一个时间
+    fn first_timestamp_after(&self, file: &mut File, offset: u64) -> std::io::Result<Option<NaiveDateTime>> {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(&*file);
+        let mut buf = Vec::new();
+        let mut read = 0;
+        if offset > 0 {
+            read += reader.read_until(b'\n', &mut buf)?; // 跳过不完整的行
+        }
+        while read < PROBE_BYTES {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+            if let Some(ts) = self.parse_line(&String::from_utf8_lossy(&buf)) {
+                return Ok(Some(ts));
+            }
+        }
+        Ok(None)
+    }
+
+    // 二分查找一个不晚于 start 的起始偏移；探测不到时间时向前收缩，保证不会漏掉窗口内的行
+    pub(crate) fn seek_offset(&self, file: &mut File, start: NaiveDateTime) -> std::io::Result<u64> {
+        let (mut lo, mut hi) = (0u64, file.metadata()?.len());
+        while hi - lo > SEEK_GRANULARITY {
+            let mid = lo + (hi - lo) / 2;
+            match self.first_timestamp_after(file, mid)? {
+                Some(ts) if ts < start => lo = mid,
+                _ => hi = mid,
+            }
+        }
+        Ok(lo)
+    }
+}
+
+// 解析服务配置的时区偏移（如 +08:00），默认使用边缘节点本地时区
+pub(crate) fn parse_offset(offset: Option<&str>) -> Result<FixedOffset, String> {
+    match offset {
+        Some(offset) => offset
+            .parse::<FixedOffset>()
+            .map_err(|e| format!("invalid utc_offset '{}': {}", offset, e)),
+        None => Ok(Local::now().offset().fix()),
+    }
+}
+
+// 请求中的时间：带时区的 RFC 3339 转换到日志所在时区，不带时区的按日志时区理解
+pub(crate) fn parse_request_time(value: &str, offset: FixedOffset) -> Result<NaiveDateTime, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&offset).naive_local());
+    }
+    REQUEST_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .ok_or_else(|| format!("invalid time '{}', expected RFC 3339 or YYYY-MM-DD HH:MM:SS", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+    fn time(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn converts_formats_to_regex() {
+        assert_eq!(format_to_regex("%Y-%m-%d").unwrap(), r"\d{4}\-\d{2}\-\d{2}");
+        let regex = Regex::new(&format_to_regex("[%d/%b/%Y:%T]").unwrap()).unwrap();
+        assert_eq!(regex.find("1.2.3.4 - [02/Jan/2025:10:00:00] GET /").unwrap().as_str(), "[02/Jan/2025:10:00:00]");
+        let regex = Regex::new(&format_to_regex(FORMAT).unwrap()).unwrap();
+        assert!(regex.is_match("2025-01-02 10:00:00.123"));
+        assert!(!regex.is_match("2025-01-02 10:00:00"));
+
+        assert!(format_to_regex("%Y %Z").is_err());
+        assert!(format_to_regex("%H:%M:%S%.2f").is_err());
+        assert!(format_to_regex("%Y-%m-%d %").is_err());
+    }
+
+    #[test]
+    fn detects_the_most_common_format() {
+        let lines: Vec<String> = [
+            "10.0.0.1 - - [02/Jan/2025:10:00:00 +0800] \"GET / HTTP/1.1\" 200",
+            "10.0.0.2 - - [02/Jan/2025:10:00:01 +0800] \"GET /a HTTP/1.1\" 404",
+            "continuation line without a timestamp",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let parser = TimestampParser::detect(&lines).unwrap();
+        assert_eq!(parser.format(), "%d/%b/%Y:%H:%M:%S");
+        assert_eq!(parser.parse_line(&lines[1]), Some(time("2025-01-02 10:00:01")));
+        assert_eq!(parser.parse_line(&lines[2]), None);
+
+        let parser = TimestampParser::detect(&["2025-01-02T10:00:00.5 INFO started".to_string()]).unwrap();
+        assert_eq!(parser.format(), "%Y-%m-%dT%H:%M:%S%.f");
+        assert!(TimestampParser::detect(&["no timestamp here".to_string()]).is_none());
+    }
+
+    #[test]
+    fn parses_request_times() {
+        let offset = parse_offset(Some("+08:00")).unwrap();
+        assert_eq!(parse_request_time("2025-01-02T02:00:00Z", offset).unwrap(), time("2025-01-02 10:00:00"));
+        assert_eq!(parse_request_time("2025-01-02T10:00:00+08:00", offset).unwrap(), time("2025-01-02 10:00:00"));
+        assert_eq!(parse_request_time("2025-01-02 10:00:00", offset).unwrap(), time("2025-01-02 10:00:00"));
+        assert_eq!(parse_request_time("2025-01-02 10:00", offset).unwrap(), time("2025-01-02 10:00:00"));
+        assert!(parse_request_time("yesterday", offset).is_err());
+        assert!(parse_offset(Some("UTC+8")).is_err());
+    }
+
+    // 每秒一行，每 10 行夹一行没有时间的堆栈；gap 为 true 时在第 15000 行后插入一段超过 PROBE_BYTES 的无时间内容
+    fn write_log(path: &std::path::Path, gap: bool) -> Vec<(NaiveDateTime, u64)> {
+        let mut lines = Vec::new();
+        let mut content = Vec::new();
+        let first = time("2025-01-02 00:00:00");
+        for n in 0..30_000 {
+            let ts = first + chrono::Duration::seconds(n);
+            lines.push((ts, content.len() as u64));
+            writeln!(content, "{} INFO request {} {}", ts.format(FORMAT), n, "x".repeat(60)).unwrap();
+            if n % 10 == 0 {
+                writeln!(content, "    at com.example.Handler.handle(Handler.java:{})", n).unwrap();
+            }
+            if gap && n == 15_000 {
+                for _ in 0..2_000 {
+                    writeln!(content, "    ... {} more", "y".repeat(60)).unwrap();
+                }
+            }
+        }
+        File::create(path).unwrap().write_all(&content).unwrap();
+        lines
+    }
+
+    #[test]
+    fn seeks_close_to_the_start_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let lines = write_log(&path, false);
+        let mut file = File::open(&path).unwrap();
+        let size = file.metadata().unwrap().len();
+        let parser = TimestampParser::new(FORMAT).unwrap();
+
+        // 起点在文件中间：返回的偏移不晚于第一条匹配行，且相差不超过查找精度
+        for n in [1, 1_000, 15_000, 29_999] {
+            let (start, target) = lines[n];
+            let offset = parser.seek_offset(&mut file, start).unwrap();
+            assert!(offset <= target && target - offset <= SEEK_GRANULARITY, "line {}: {} vs {}", n, offset, target);
+        }
+
+        // 早于第一行：从文件开头开始
+        assert_eq!(parser.seek_offset(&mut file, time("2024-12-31 00:00:00")).unwrap(), 0);
+
+        // 晚于最后一行：只剩文件末尾的一小段需要扫描
+        let offset = parser.seek_offset(&mut file, time("2025-01-03 00:00:00")).unwrap();
+        assert!(size - offset <= SEEK_GRANULARITY);
+    }
+
+    #[test]
+    fn never_seeks_past_lines_without_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let lines = write_log(&path, true);
+        let mut file = File::open(&path).unwrap();
+        let parser = TimestampParser::new(FORMAT).unwrap();
+
+        // 探测落在无时间内容中时向前收缩，可能离起点较远，但不会跳过匹配行
+        for n in [1_000, 14_990, 15_001, 15_100, 29_999] {
+            let (start, target) = lines[n];
+            let offset = parser.seek_offset(&mut file, start).unwrap();
+            assert!(offset <= target, "line {}: {} > {}", n, offset, target);
+        }
+    }
+}
@@ -1,5 +1,11 @@
-use crate::modify_filebeat_yaml::{modify_yaml,modify_yaml_dynamic};
+use crate::download;
+use crate::file_read;
+use crate::grep;
+use crate::health;
+use crate::metrics;
+use crate::modify_filebeat_yaml::modify_yaml_dynamic;
 use crate::system_cmd;
+use crate::timestamp::{parse_offset, parse_request_time, TimestampParser};
 use async_std::net::{SocketAddr, TcpListener, TcpStream};
 use async_tungstenite::{
     accept_async,
@@ -8,14 +14,13 @@ use async_tungstenite::{
 };
 use futures::prelude::*;
 use log::*;
-use serde::{Deserialize, Deserializer, Serialize};
-use serde_json::Value;
-use serde_yaml;
-use std::fs::read_dir;
-use std::ptr::read;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{fs, sync::Arc};
-use std::any::Any;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use std::env;
 
 type SharedClients = Arc<Mutex<Vec<Arc<Mutex<WebSocketStream<TcpStream>>>>>>;
@@ -24,12 +29,20 @@ type Broadcaster = broadcast::Sender<Message>;
 #[derive(Debug, Deserialize, Clone)]
 struct Config {
     log_inputs: Vec<ServiceType>,
+    #[serde(default)]
+    limits: grep::GrepLimits,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct ServiceType {
     service_type: String,
     path: Vec<String>,
+    #[serde(default)]
+    timestamp_format: Option<String>, // 日志行时间格式（chrono 格式），未配置时自动识别
+    #[serde(default)]
+    utc_offset: Option<String>,       // 日志时间所在时区，如 +08:00，默认使用本机时区
+    #[serde(default)]
+    multiline: Option<grep::MultilineConfig>, // 多行事件规则，用于 file_grep 和生成的 Filebeat 输入配置
 }
 
 #[derive(Serialize, Clone)]
@@ -50,6 +63,109 @@ pub struct WebSocketServer {
     clients: SharedClients,
     tx: Broadcaster,
     config: Option<Config>, // Store config after loading once
+    config_error: Option<String>, // 配置加载失败的原因，通过 health 命令上报
+    grep_slots: Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>, // 每个客户端 IP 正在执行的 file_grep 数量
+}
+
+// 占用一个 file_grep 名额，离开作用域时释放
+struct GrepSlot {
+    slots: Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl GrepSlot {
+    fn acquire(slots: &Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>, ip: IpAddr, max: usize) -> Option<GrepSlot> {
+        let mut slots_guard = slots.lock().unwrap();
+        let running = slots_guard.entry(ip).or_default();
+        if *running >= max {
+            return None;
+        }
+        *running += 1;
+        Some(GrepSlot { slots: slots.clone(), ip })
+    }
+}
+
+impl Drop for GrepSlot {
+    fn drop(&mut self) {
+        let mut slots_guard = self.slots.lock().unwrap();
+        if let Some(running) = slots_guard.get_mut(&self.ip) {
+            *running -= 1;
+            if *running == 0 {
+                slots_guard.remove(&self.ip);
+            }
+        }
+    }
+}
+
+// 按请求中的 service_type 或文件所在目录找到文件所属的服务
+fn find_service<'a>(config: Option<&'a Config>, request: &Value, file_path: &str) -> Option<&'a ServiceType> {
+    config.and_then(|config| {
+        config.log_inputs.iter().find(|service| match request["service_type"].as_str() {
+            Some(service_type) => service.service_type == service_type,
+            None => service.path.iter().any(|dir| file_path.starts_with(dir.as_str())),
+        })
+    })
+}
+
+// file_grep 的 start_time / end_time，按服务配置的时间格式和时区解析
+fn time_window(config: Option<&Config>, request: &Value, file_path: &str) -> Result<Option<grep::TimeWindow>, Vec<String>> {
+    let (start, end) = (request["start_time"].as_str(), request["end_time"].as_str());
+    if start.is_none() && end.is_none() {
+        return Ok(None);
+    }
+    let service = find_service(config, request, file_path);
+
+    let mut errors = Vec::new();
+    let offset = parse_offset(service.and_then(|s| s.utc_offset.as_deref())).unwrap_or_else(|e| {
+        errors.push(e);
+        chrono::FixedOffset::east_opt(0).unwrap()
+    });
+    let mut parse = |field: &str, value: Option<&str>| {
+        value.and_then(|value| parse_request_time(value, offset).map_err(|e| errors.push(format!("{}: {}", field, e))).ok())
+    };
+    let (start, end) = (parse("start_time", start), parse("end_time", end));
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            errors.push("start_time must not be later than end_time".to_string());
+        }
+    }
+    let parser = match service.and_then(|s| s.timestamp_format.as_deref()) {
+        Some(format) => TimestampParser::new(format)
+            .map_err(|e| errors.push(format!("timestamp_format: {}", e)))
+            .ok(),
+        None => None,
+    };
+
+    if errors.is_empty() {
+        Ok(Some(grep::TimeWindow { parser, start, end }))
+    } else {
+        Err(errors)
+    }
+}
+
+// 规范化路径后检查是否位于某个服务配置的日志目录下，防止通过 ../ 读取任意文件
+fn is_allowed_path(config: Option<&Config>, file_path: &str) -> bool {
+    let Ok(file_path) = fs::canonicalize(file_path) else {
+        return false;
+    };
+    config.is_some_and(|config| {
+        config
+            .log_inputs
+            .iter()
+            .flat_map(|service| service.path.iter())
+            .filter_map(|dir| fs::canonicalize(dir).ok())
+            .any(|dir| file_path.starts_with(dir))
+    })
+}
+
+fn config_path() -> String {
+    env::var("LOG_FILE_PATH").unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/filebeat_restful/config/log.yaml".to_string())
+}
+
+impl Default for WebSocketServer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WebSocketServer {
@@ -59,16 +175,25 @@ impl WebSocketServer {
             clients: Arc::new(Mutex::new(Vec::new())),
             tx,
             config: None,
+            config_error: None,
+            grep_slots: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
 
     pub async fn load_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if self.config.is_none() {
-            let file_path = env::var("LOG_FILE_PATH").unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/filebeat_restful/config/log.yaml".to_string());
+            let file_path = config_path();
             let config_data = fs::read_to_string(file_path)?;
             info!("load_config: {}", config_data);
             let config: Config = serde_yaml::from_str(&config_data)
                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?; // Explicitly convert error
+            for service in &config.log_inputs {
+                if let Some(multiline) = &service.multiline {
+                    multiline
+                        .compile()
+                        .map_err(|e| format!("log_inputs {}: multiline: {}", service.service_type, e))?;
+                }
+            }
             self.config = Some(config);
         }
         Ok(())
@@ -89,13 +214,14 @@ impl WebSocketServer {
         let listener = TcpListener::bind(&addr).await.expect("Can't listen");
         info!("WebSocket service is listening on: {}", addr);
 
-        // 先加载配置
+        // 先加载配置；加载失败时仍然提供服务，以便通过 health 命令查看原因
         if let Err(e) = self.load_config().await {
-            info!("Error loading config: {}", e);
-            return;
+            error!("Error loading config: {}", e);
+            self.config_error = Some(e.to_string());
         }
 
-        let server_arc = Arc::new(Mutex::new(self.clone()));
+        // 配置加载后服务端状态只读，各连接并发处理
+        let server_arc = Arc::new(self.clone());
 
         while let Ok((stream, _)) = listener.accept().await {
             let peer = stream
@@ -108,14 +234,15 @@ impl WebSocketServer {
 
             let server_arc_clone = Arc::clone(&server_arc);
 
+            metrics::ACTIVE_CONNECTIONS.inc();
             tokio::spawn({
                 async move {
-                    let server = server_arc_clone.lock().await;
                     info!("before call accept_connection");
-                    server
+                    server_arc_clone
                         .accept_connection(peer, stream, clients_clone, tx_clone)
                         .await;
                     info!("after call accept_connection");
+                    metrics::ACTIVE_CONNECTIONS.dec();
                 }
             });
         }
@@ -149,41 +276,54 @@ impl WebSocketServer {
         let ws_stream = Arc::new(Mutex::new(ws_stream)); // Wrap WebSocketStream inside a Mutex
 
         info!("New WebSocket connection: {}", peer);
-        let mut clients_lock = clients.lock().await;
-        clients_lock.push(ws_stream.clone()); // Use Arc::clone to share the reference
-
-        loop {
-            tokio::select! {
-            Some(msg) = async {
-                let mut ws_guard = ws_stream.lock().await; // Lock the WebSocketStream for mutable access
-                ws_guard.next().await
-                } => {
-                    match msg {
-                        Ok(msg) => {
-                            self.handle_client_message(msg, peer, &tx, &ws_stream).await;
-                            return Ok(());
-                        },
-                        Err(e) => {
-                            info!("Error processing message: {}", e);
-                            return Err(e);
-                        }};
-                }
+        clients.lock().await.push(ws_stream.clone()); // Use Arc::clone to share the reference
+
+        let msg = {
+            let mut ws_guard = ws_stream.lock().await; // Lock the WebSocketStream for mutable access
+            ws_guard.next().await
+        };
+        let result = match msg {
+            Some(Ok(msg)) => {
+                self.handle_client_message(msg, peer, &tx, &ws_stream).await;
+                Ok(())
             }
-        }
-        info!("after tokio::select handle_client_message");
+            Some(Err(e)) => {
+                info!("Error processing message: {}", e);
+                Err(e)
+            }
+            None => Ok(()),
+        };
+        clients.lock().await.retain(|client| !Arc::ptr_eq(client, &ws_stream));
+        result
     }
 
     async fn handle_client_message(
         &self,
         msg: Message,
         peer: SocketAddr,
-        tx: &Broadcaster,
+        _tx: &Broadcaster,
         client_ws: &Arc<Mutex<WebSocketStream<TcpStream>>>,
     ) {
         if msg.is_text() {
             let text = msg.to_text().unwrap();
+            let is_health = text == "health"
+                || serde_json::from_str::<Value>(text).is_ok_and(|v| v["cmd"].as_str() == Some("health"));
+            if is_health {
+                info!("Received cmd: health from {}", peer);
+                metrics::COMMANDS.with_label_values(&["health"]).inc();
+                let log_dirs: Vec<String> = self
+                    .config
+                    .iter()
+                    .flat_map(|config| config.log_inputs.iter().flat_map(|inputs_kv| inputs_kv.path.clone()))
+                    .collect();
+                let report = health::health_report(&config_path(), self.config_error.as_deref(), &log_dirs).await;
+                let mut client_ws_guard = client_ws.lock().await;
+                let _ = client_ws_guard.send(Message::Text(report.to_string())).await;
+                return;
+            }
             if text == "get_log_source" {
                 info!("Received cmd：{} from {} for get log files", text, peer);
+                metrics::COMMANDS.with_label_values(&["get_log_source"]).inc();
                 if let Some(config) = &self.config {
                     let log_files: Vec<ServiceFiles> = config
                         .log_inputs
@@ -212,23 +352,36 @@ impl WebSocketServer {
                 }
             }
 
-            if let Ok(json_data) = serde_json::from_str::<Value>(&text) {
+            if let Ok(json_data) = serde_json::from_str::<Value>(text) {
                 if json_data["cmd"].as_str() == Some("firebase_upload") {
+                    metrics::COMMANDS.with_label_values(&["firebase_upload"]).inc();
                     let file_path = env::var("FILEBEAT_CONFIG_LOG_PATH").unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/filebeat_restful/filebeat/inputs.d/log.yml".to_string());
                     let new_paths = vec![json_data["upload_file"].as_str().unwrap_or_default().to_string()];
                     let new_hostname = json_data["hostname"].as_str().unwrap_or_default().to_string();
                     let new_service = json_data["service"].as_str().unwrap_or_default().to_string();
                     info!("Received cmd: firebase_upload from {},need change file_path:{}, new_paths:{}, new_service:{}, new_hostname:{}", peer,file_path,new_paths[0],new_service,new_hostname);
-                    modify_yaml_dynamic(file_path.as_str(), new_paths, new_service, new_hostname);
+                    let multiline = self
+                        .config
+                        .as_ref()
+                        .and_then(|config| config.log_inputs.iter().find(|service| service.service_type == new_service))
+                        .and_then(|service| service.multiline.as_ref());
+                    let modified = modify_yaml_dynamic(file_path.as_str(), new_paths, new_service, new_hostname, multiline)
+                        .map_err(|e| e.to_string());
+                    metrics::UPLOAD_JOBS.with_label_values(&[metrics::result_label(&modified)]).inc();
+                    if let Err(e) = modified {
+                        info!("modify_yaml_dynamic Error: {}", e);
+                    }
                     let response_msg = Message::Text("firebase_upload ing".to_string());
                     let mut client_ws_guard = client_ws.lock().await;
-                    client_ws_guard.send(response_msg).await;
+                    let _ = client_ws_guard.send(response_msg).await;
 
                     let filebeat_config_path = env::var("FILEBEAT_CONFIG_MAIN_PATH")
                         .unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/filebeat_restful/filebeat/filebeat.yml".to_string());
                     info!("filebeat_config_path: {}", filebeat_config_path);
                     if env::var("FILEBEAT_CONFIG_MAIN_PATH").is_ok() {
-                        match system_cmd::start_filebeat(filebeat_config_path.as_str()).await {
+                        let started = system_cmd::start_filebeat(filebeat_config_path.as_str()).await;
+                        metrics::FILEBEAT_RESTARTS.with_label_values(&[metrics::result_label(&started)]).inc();
+                        match started {
                             Ok(()) => {
                                 info!("Filebeat started successfully with config: {}", filebeat_config_path);
                                 return;
@@ -239,7 +392,9 @@ impl WebSocketServer {
                             }
                         }
                     } else {
-                        match system_cmd::get_and_restart_container("filebeat").await {
+                        let restarted = system_cmd::get_and_restart_container("filebeat").await;
+                        metrics::FILEBEAT_RESTARTS.with_label_values(&[metrics::result_label(&restarted)]).inc();
+                        match restarted {
                             Ok(()) => {
                                 info!("Filebeat container restarted successfully.");
                                 return;
@@ -252,37 +407,255 @@ impl WebSocketServer {
                     }
                 }
                 if json_data["cmd"].as_str() == Some("file_grep") {
-                    // 提取 filter_strings 并转换为 Vec<String>
-                    let filter_strings: Vec<String> = json_data["filter_strings"]
-                        .as_array()  // 尝试从 JSON 中获取一个数组
-                        .and_then(|arr| {
-                            // 如果 arr 存在，返回一个新的 Vec<String>，否则返回 None
-                            Some(arr.iter().map(|v| v.to_string()).collect())
+                    metrics::COMMANDS.with_label_values(&["file_grep"]).inc();
+                    // 提取 file_path 字段，若没有则返回默认空字符串
+                    let file_path = json_data["file_path"].as_str().unwrap_or_default().to_string();
+                    let context_line = json_data["context_line"].as_u64().unwrap_or_default() as usize;
+
+                    // 打印日志
+                    info!("Received cmd: file_grep  file_path: {}, patterns: {}, filter_strings: {}, start_time: {}, end_time: {}", file_path, json_data["patterns"], json_data["filter_strings"], json_data["start_time"], json_data["end_time"]);
+
+                    // 与 file_read / file_download 相同，只允许读取配置的日志目录下的文件
+                    if !is_allowed_path(self.config.as_ref(), &file_path) {
+                        let response = json!({ "cmd": "file_grep", "file_path": file_path, "error": "File is not under a configured log directory" });
+                        let mut client_ws_guard = client_ws.lock().await;
+                        let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
+                        return;
+                    }
+
+                    // 匹配条件、时间范围校验失败或读取文件失败时返回 JSON 错误
+                    let window = match time_window(self.config.as_ref(), &json_data, &file_path) {
+                        Ok(window) => window,
+                        Err(details) => {
+                            let response = json!({ "cmd": "file_grep", "error": "Invalid time range", "details": details });
+                            let mut client_ws_guard = client_ws.lock().await;
+                            let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
+                            return;
+                        }
+                    };
+                    let filter = match grep::LineFilter::from_request(&json_data) {
+                        Ok(filter) => filter,
+                        Err(details) => {
+                            let response = json!({ "cmd": "file_grep", "error": "Invalid patterns", "details": details });
+                            let mut client_ws_guard = client_ws.lock().await;
+                            let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
+                            return;
+                        }
+                    };
+                    let limits = self.config.as_ref().map(|config| config.limits).unwrap_or_default();
+                    let Some(_slot) = GrepSlot::acquire(&self.grep_slots, peer.ip(), limits.max_concurrent_greps_per_client) else {
+                        let response = json!({
+                            "cmd": "file_grep",
+                            "error": format!("Too many concurrent file_grep requests from this client (limit {})", limits.max_concurrent_greps_per_client)
+                        });
+                        let mut client_ws_guard = client_ws.lock().await;
+                        let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
+                        return;
+                    };
+
+                    // grep 执行期间继续读取连接，客户端断开时通知扫描线程提前结束
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    // 服务配置了多行规则时按事件匹配，请求中 "multiline": false 可关闭
+                    let multiline = find_service(self.config.as_ref(), &json_data, &file_path)
+                        .filter(|_| json_data["multiline"].as_bool() != Some(false))
+                        .and_then(|service| service.multiline.as_ref())
+                        .and_then(|multiline| multiline.compile().ok());
+                    let request = grep::GrepRequest { filter, context: context_line, window, multiline };
+                    let grep = grep::grep_file(file_path.clone(), request, limits, cancelled.clone());
+                    let result = {
+                        tokio::pin!(grep);
+                        let mut client_ws_guard = client_ws.lock().await;
+                        loop {
+                            tokio::select! {
+                                result = &mut grep => break result,
+                                msg = client_ws_guard.next() => {
+                                    if matches!(msg, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                                        info!("Client {} disconnected, cancelling file_grep on {}", peer, file_path);
+                                        cancelled.store(true, Ordering::Relaxed);
+                                        break grep.await;
+                                    }
+                                }
+                            }
+                        }
+                    };
+                    if let Ok(output) = &result {
+                        metrics::GREP_BYTES_SCANNED.inc_by(output.bytes_scanned);
+                        if let Some(truncated) = output.truncated {
+                            metrics::GREP_TRUNCATED.with_label_values(&[truncated.name()]).inc();
+                        }
+                    }
+                    if cancelled.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    // 默认返回纯文本，被截断时在末尾附加说明行；format 为 json 时返回结构化结果
+                    let as_json = json_data["format"].as_str() == Some("json");
+                    let response_text = match result {
+                        Ok(output) => {
+                            if as_json {
+                                json!({
+                                    "cmd": "file_grep",
+                                    "file_path": file_path,
+                                    "output": output.text,
+                                    "matched_lines": output.matched_lines,
+                                    "bytes_scanned": output.bytes_scanned,
+                                    "truncated": output.truncated.is_some(),
+                                    "truncation_reason": output.truncated.map(|t| t.name()),
+                                })
+                                .to_string()
+                            } else {
+                                let mut text = output.text;
+                                if let Some(truncated) = output.truncated {
+                                    text.push_str(&format!(
+                                        "-- truncated ({}): {} matched lines, {} bytes scanned --\n",
+                                        truncated.name(),
+                                        output.matched_lines,
+                                        output.bytes_scanned
+                                    ));
+                                }
+                                text
+                            }
+                        }
+                        Err(e) => json!({
+                            "cmd": "file_grep",
+                            "error": format!("Failed to read {}: {}", file_path, e)
                         })
-                        .unwrap_or_default();  // 如果没有 filter_strings 或不是数组，使用空的 Vec<String>
+                        .to_string(),
+                    };
 
+                    // 将 grep 结果作为响应消息发送到客户端
+                    let response_msg = Message::Text(response_text);
+                    let mut client_ws_guard = client_ws.lock().await;
+                    let _ = client_ws_guard.send(response_msg).await;
+                }
+                if json_data["cmd"].as_str() == Some("file_download") {
+                    metrics::COMMANDS.with_label_values(&["file_download"]).inc();
+                    let file_path = json_data["file_path"].as_str().unwrap_or_default().to_string();
+                    info!("Received cmd: file_download from {} file_path: {}, request: {}", peer, file_path, json_data);
 
-                    // 提取 file_path 字段，若没有则返回默认空字符串
+                    let opened = if !is_allowed_path(self.config.as_ref(), &file_path) {
+                        Err("File is not under a configured log directory".to_string())
+                    } else {
+                        download::DownloadRange::from_request(&json_data).and_then(|range| {
+                            download::open(&file_path, range).map_err(|e| format!("Failed to read {}: {}", file_path, e))
+                        })
+                    };
+                    let mut client_ws_guard = client_ws.lock().await;
+                    let (header, reader) = match opened {
+                        Ok(opened) => opened,
+                        Err(e) => {
+                            let response = json!({ "cmd": "file_download", "file_path": file_path, "error": e });
+                            let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
+                            return;
+                        }
+                    };
+                    let mut response = json!(header);
+                    response["cmd"] = json!("file_download");
+                    response["file_path"] = json!(file_path);
+                    if client_ws_guard.send(Message::Text(response.to_string())).await.is_err() {
+                        return;
+                    }
+
+                    // 读取和压缩在阻塞线程中进行，通过有界通道逐块发送给客户端
+                    let (chunks_tx, mut chunks_rx) = mpsc::channel(4);
+                    let streaming = tokio::task::spawn_blocking(move || download::stream_blocking(reader, chunks_tx));
+                    while let Some(chunk) = chunks_rx.recv().await {
+                        metrics::DOWNLOAD_BYTES.inc_by(chunk.len() as u64);
+                        if client_ws_guard.send(Message::Binary(chunk)).await.is_err() {
+                            info!("Client {} disconnected, stopping file_download of {}", peer, file_path);
+                            break;
+                        }
+                    }
+                    drop(chunks_rx);
+                    let trailer = match streaming.await.map_err(std::io::Error::other).and_then(|result| result) {
+                        Ok(summary) => {
+                            let mut trailer = json!(summary);
+                            trailer["cmd"] = json!("file_download");
+                            trailer["done"] = json!(true);
+                            trailer
+                        }
+                        Err(e) => json!({ "cmd": "file_download", "file_path": file_path, "error": format!("Failed to read {}: {}", file_path, e) }),
+                    };
+                    let _ = client_ws_guard.send(Message::Text(trailer.to_string())).await;
+                }
+                if json_data["cmd"].as_str() == Some("file_read") {
+                    metrics::COMMANDS.with_label_values(&["file_read"]).inc();
                     let file_path = json_data["file_path"].as_str().unwrap_or_default().to_string();
-                    let context_line = json_data["context_line"].as_i64().unwrap_or_default();
+                    info!("Received cmd: file_read from {} file_path: {}, request: {}", peer, file_path, json_data);
 
-                    // 打印日志
-                    info!("Received cmd: file_grep  file_path: {}, filter_strings: {:?}", file_path, filter_strings);
-                    let grep_result = system_cmd::grep_multiple_layers(&file_path, filter_strings,context_line).await;
+                    // 服务配置了时间格式时，行索引按该格式记录各块的时间范围
+                    let parser = find_service(self.config.as_ref(), &json_data, &file_path)
+                        .and_then(|service| service.timestamp_format.as_deref())
+                        .and_then(|format| TimestampParser::new(format).ok());
 
-                    // 将 grep_result 作为响应消息发送到客户端
-                    let response_msg = Message::Text(grep_result.expect("REASON"));
+                    // 只允许读取配置的日志目录下的文件
+                    let response = if !is_allowed_path(self.config.as_ref(), &file_path) {
+                        json!({ "cmd": "file_read", "file_path": file_path, "error": "File is not under a configured log directory" })
+                    } else {
+                        match file_read::ReadRange::from_request(&json_data) {
+                            Err(e) => json!({ "cmd": "file_read", "file_path": file_path, "error": e }),
+                            Ok(range) => match file_read::read_chunk(file_path.clone(), range, parser).await {
+                                Ok(chunk) => {
+                                    let mut response = json!(chunk);
+                                    response["cmd"] = json!("file_read");
+                                    response["file_path"] = json!(file_path);
+                                    response
+                                }
+                                Err(e) => json!({
+                                    "cmd": "file_read",
+                                    "file_path": file_path,
+                                    "error": format!("Failed to read {}: {}", file_path, e)
+                                }),
+                            },
+                        }
+                    };
                     let mut client_ws_guard = client_ws.lock().await;
-                    client_ws_guard.send(response_msg).await;
+                    let _ = client_ws_guard.send(Message::Text(response.to_string())).await;
                 }
-                return;
             }
         } else if msg.is_binary() {
             info!("Received binary message from {}", peer);
         } else if msg.is_close() {
             info!("Received is_close");
             let mut client_ws_guard = client_ws.lock().await;
-            client_ws_guard.close(None).await;
+            let _ = client_ws_guard.close(None).await;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &std::path::Path) -> Config {
+        Config {
+            log_inputs: vec![ServiceType {
+                service_type: "RTC".to_string(),
+                path: vec![dir.to_string_lossy().to_string()],
+                timestamp_format: None,
+                utc_offset: None,
+                multiline: None,
+            }],
+            limits: grep::GrepLimits::default(),
+        }
+    }
+
+    // file_grep / file_read / file_download 共用的路径检查
+    #[test]
+    fn rejects_paths_outside_log_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let logs = root.path().join("logs");
+        fs::create_dir(&logs).unwrap();
+        fs::write(logs.join("app.log"), "line\n").unwrap();
+        fs::write(root.path().join("secret.key"), "key\n").unwrap();
+        let config = config(&logs);
+
+        assert!(is_allowed_path(Some(&config), logs.join("app.log").to_str().unwrap()));
+        assert!(!is_allowed_path(Some(&config), root.path().join("secret.key").to_str().unwrap()));
+        assert!(!is_allowed_path(Some(&config), logs.join("../secret.key").to_str().unwrap()));
+        assert!(!is_allowed_path(Some(&config), "/etc/shadow"));
+        assert!(!is_allowed_path(Some(&config), logs.join("missing.log").to_str().unwrap()));
+        // 没有配置时全部拒绝
+        assert!(!is_allowed_path(None, logs.join("app.log").to_str().unwrap()));
+    }
+}
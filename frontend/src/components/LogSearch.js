@@ -11,11 +11,10 @@ const {Text} = Typography;
 const LogSearch = () => {
     const socketRef = useRef(null);
 
-    const [indices, setIndices] = useState([]);
-    const [es_index, setEsIndex] = useState("");
     const [filters, setFilters] = useState({
         hostname: "",
         service: "",
+        basename: "",  // 只由保存的查询回填
     });
     const [availableFilters, setAvailableFilters] = useState({
         hostname: [],
@@ -25,7 +24,8 @@ const LogSearch = () => {
     const [filterLoading, setFilterLoading] = useState(false);
 
     const [loading, setLoading] = useState(false);
-    const [searchQuery, setSearchQuery] = useState("");
+    const [searchQuery, setSearchQuery] = useState("");  // 关键字，对应 keyword
+    const [queryExpression, setQueryExpression] = useState("");  // 查询表达式，对应 query
     const [results, setResults] = useState([]);
     const [startTime, setStartTime] = useState("");
     const [endTime, setEndTime] = useState("");
@@ -35,27 +35,60 @@ const LogSearch = () => {
 
     useEffect(() => {
         refreshElasticSearch();
+        loadSavedSearch();
     }, []);
 
+    // 分享链接 /admin/?saved={id}：回填保存的查询条件并执行
+    const loadSavedSearch = () => {
+        const id = new URLSearchParams(window.location.search).get('saved');
+        if (!id) {
+            return;
+        }
+        axios.get(`${API_BASE_URL}/saved_searches/${encodeURIComponent(id)}`)
+            .then(response => {
+                const request = response.data.request || {};
+                setSearchQuery(request.keyword || "");
+                setQueryExpression(request.query || "");
+                setFilters({
+                    hostname: request.hostname || "",
+                    service: request.service || "",
+                    basename: request.basename || "",
+                });
+                setStartTime(request.start_time || "");
+                setEndTime(request.end_time || "");
+                return axios.post(`${API_BASE_URL}/saved_searches/${encodeURIComponent(id)}/run`);
+            })
+            .then(response => {
+                setResults(response.data.results);
+            })
+            .catch(error => {
+                message.error(error.response?.data?.error || "Failed to load saved search");
+            });
+    };
+
+    // 索引由 logs_filter 根据所选服务和时间范围解析，不再由页面选择
     const fetchFieldOptions = (field) => {
         setFilterLoading(true);
-        axios.get(`${API_BASE_URL}/indices?index_pattern=${es_index}&field=${field}`)
+        return axios.get(`${API_BASE_URL}/field_values`, {
+            params: {
+                field,
+                service: field === 'service' ? undefined : filters.service || undefined,
+                start_time: startTime || undefined,
+                end_time: endTime || undefined
+            }
+        })
             .then(response => {
-                const fieldName = field.split('.')[0];
                 setAvailableFilters(prevFilters => ({
                     ...prevFilters,
-                    [fieldName]: response.data.unique_services || []
+                    [field]: (response.data.values || []).map(item => item.value)
                 }));
             })
             .finally(() => setFilterLoading(false));
     };
 
     useEffect(() => {
-        if (es_index) {
-            fetchFieldOptions('hostname.keyword');
-            fetchFieldOptions('service.keyword');
-        }
-    }, [es_index]);
+        fetchFieldOptions('hostname');
+    }, [filters.service]);
 
     const handleFilterChange = (value, field) => {
         setFilters(prevFilters => ({
@@ -69,30 +102,45 @@ const LogSearch = () => {
     };
 
     const handleSearch = () => {
-        if (!searchQuery || !es_index) {
+        if (!searchQuery && !queryExpression) {
             message.error("All fields are required.");
             return;
         }
 
-        // 调用 API 搜索，传递 es_index 代替 selectedIndex
-        axios.post(`${API_BASE_URL}/keyword_search`, {
-            keyword: searchQuery,
-            es_index: es_index,  // 传递 es_index
-        }).then(response => {
+        // 有查询表达式或主机名、文件名过滤时使用 /search，它需要时间范围
+        const structured = queryExpression || filters.hostname || filters.basename;
+        if (structured && !(startTime && endTime)) {
+            message.error("Start and end time are required.");
+            return;
+        }
+
+        // 调用 API 搜索，索引由服务和时间范围决定
+        const request = structured
+            ? axios.post(`${API_BASE_URL}/search`, {
+                keyword: searchQuery,
+                query: queryExpression,
+                hostname: filters.hostname,
+                service: filters.service,
+                basename: filters.basename,
+                start_time: startTime,
+                end_time: endTime,
+            })
+            : axios.post(`${API_BASE_URL}/keyword_search`, {
+                keyword: searchQuery,
+                service: filters.service || undefined,
+                start_time: startTime || undefined,
+                end_time: endTime || undefined,
+            });
+        request.then(response => {
             setResults(response.data.results);
+        }).catch(error => {
+            message.error(error.response?.data?.error || "Search failed");
         });
     };
 
     const refreshElasticSearch = () => {
         setLoading(true);
-        axios.get(`${API_BASE_URL}/get_indices`)
-            .then(response => {
-                const indexList = response.data.indices;
-                setIndices(indexList);
-                if (indexList.length > 0) {
-                    setEsIndex(indexList[0]);
-                }
-            })
+        Promise.all([fetchFieldOptions('service'), fetchFieldOptions('hostname')])
             .finally(() => setLoading(false));
     };
 
@@ -192,25 +240,10 @@ const LogSearch = () => {
     return (
         <div className="log-search-container" style={{width: '100%'}}>
             <Col style={{width: '250px', marginBottom: 25}}>
-                <Button type="primary" onClick={refreshElasticSearch}>RefreshElastic</Button>
+                <Button type="primary" onClick={refreshElasticSearch} loading={loading}>RefreshElastic</Button>
             </Col>
 
             <Row gutter={[16, 16]} align="middle" style={{display: 'flex', justifyContent: 'space-between'}}>
-                <Col style={{width: '200px'}}>
-                    <Select
-                        value={es_index}
-                        onChange={(value) => setEsIndex(value)}
-                        style={{width: '100%'}}
-                        loading={loading}
-                    >
-                        {indices.map((index, idx) => (
-                            <Option key={idx} value={index}>
-                                {index}
-                            </Option>
-                        ))}
-                    </Select>
-                </Col>
-
                 <Col style={{width: '110px'}}>
                     <Popover
                         content={filterPopoverContent}
@@ -234,6 +267,15 @@ const LogSearch = () => {
                     />
                 </Col>
 
+                <Col style={{flex: '1 1 10%'}}>
+                    <Input
+                        value={queryExpression}
+                        onChange={(e) => setQueryExpression(e.target.value)}
+                        placeholder="Query, e.g. service:RTC AND level:ERROR"
+                        style={{height: '32px'}}
+                    />
+                </Col>
+
 
                 <Col style={{width: '110px'}}>
                     <Button type="primary" onClick={handleSearch}>Search</Button>
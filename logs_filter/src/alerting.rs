@@ -0,0 +1,437 @@
+use actix_web::web;
+use elasticsearch::CountParts;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::query_parser::compile_query;
+use crate::error::es_json;
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use crate::routes::search::SearchRequest;
+use crate::saved_search_store::{now_millis, SavedSearchStore};
+
+// 告警：定时以保存的搜索（或内联的 /search 请求体）统计窗口内的文档数，
+// 按阈值判断是否触发，状态变化（触发/恢复）时向 webhook 发送通知。
+// 至少一个 webhook 发送成功后才记录状态变化，全部失败时下一次评估重试。
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_window_minutes() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn matches(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Gt => value > threshold,
+            Comparison::Gte => value >= threshold,
+            Comparison::Lt => value < threshold,
+            Comparison::Lte => value <= threshold,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    // 窗口内匹配的文档数
+    Count { op: Comparison, threshold: f64 },
+    // 窗口内同时匹配 numerator_query 的文档占比（0~1）
+    Ratio { numerator_query: String, op: Comparison, threshold: f64 },
+    // 窗口内没有任何匹配的文档
+    Absence,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    pub saved_search: Option<String>, // 保存的搜索 id
+    pub search: Option<Value>,        // 或者内联的 /search 请求体（start_time/end_time 会被窗口覆盖）
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: u64,
+    pub condition: Condition,
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    pub repeat_minutes: Option<u64>, // 持续触发时重复通知的间隔，不设置则只在状态变化时通知
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    #[default]
+    Inactive,
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct AlertState {
+    pub status: AlertStatus,
+    pub value: Option<f64>,
+    pub since: Option<u64>, // 进入当前状态的时间（epoch 毫秒）
+    pub last_evaluated: Option<u64>,
+    pub last_notified: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+// 规则名用于标识告警状态，不能重复
+pub(crate) fn check_rule_names(rules: &[AlertRule]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    match rules.iter().find(|rule| !names.insert(rule.name.as_str())) {
+        Some(rule) => Err(format!("Duplicate alert rule name: {}", rule.name)),
+        None => Ok(()),
+    }
+}
+
+impl AlertState {
+    // 根据本次评估结果更新状态，返回需要通知的状态（去重：只有状态变化或到了重复通知间隔才通知）
+    fn transition(&mut self, breached: bool, now: u64, repeat_minutes: Option<u64>) -> Option<AlertStatus> {
+        match (breached, self.status) {
+            (true, AlertStatus::Firing) => {
+                let repeat_due = repeat_minutes.is_some_and(|minutes| {
+                    now.saturating_sub(self.last_notified.unwrap_or_default()) >= minutes * 60_000
+                });
+                repeat_due.then_some(AlertStatus::Firing)
+            }
+            (true, _) => {
+                self.status = AlertStatus::Firing;
+                self.since = Some(now);
+                Some(AlertStatus::Firing)
+            }
+            (false, AlertStatus::Firing) => {
+                self.status = AlertStatus::Resolved;
+                self.since = Some(now);
+                Some(AlertStatus::Resolved)
+            }
+            (false, _) => None,
+        }
+    }
+}
+
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    interval: Duration,
+    es: web::Data<EsClient>,
+    saved_searches: web::Data<SavedSearchStore>,
+    routing: web::Data<IndexRouting>,
+    http: reqwest::Client,
+    states: Mutex<HashMap<String, AlertState>>,
+    evaluating: HashMap<String, tokio::sync::Mutex<()>>, // 每条规则同时只有一次评估，避免定时评估与手动评估重复通知
+}
+
+impl AlertEngine {
+    pub fn new(
+        rules: Vec<AlertRule>,
+        interval_secs: u64,
+        es: web::Data<EsClient>,
+        saved_searches: web::Data<SavedSearchStore>,
+        routing: web::Data<IndexRouting>,
+    ) -> AlertEngine {
+        let states = rules.iter().map(|r| (r.name.clone(), AlertState::default())).collect();
+        let evaluating = rules.iter().map(|r| (r.name.clone(), tokio::sync::Mutex::new(()))).collect();
+        AlertEngine {
+            rules,
+            interval: Duration::from_secs(interval_secs.max(1)),
+            es,
+            saved_searches,
+            routing,
+            http: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            states: Mutex::new(states),
+            evaluating,
+        }
+    }
+
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    pub fn state(&self, name: &str) -> AlertState {
+        let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        states.get(name).cloned().unwrap_or_default()
+    }
+
+    // 启动后台定时评估
+    pub fn start(engine: web::Data<AlertEngine>) {
+        if engine.rules.is_empty() {
+            return;
+        }
+        info!("Starting alert engine with {} rules", engine.rules.len());
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(engine.interval);
+            loop {
+                ticker.tick().await;
+                for rule in &engine.rules {
+                    engine.evaluate(rule).await;
+                }
+            }
+        });
+    }
+
+    // 构造规则对应的 /search 请求，时间范围替换为评估窗口
+    fn search_request(&self, rule: &AlertRule) -> Result<SearchRequest, String> {
+        let mut request = match (&rule.saved_search, &rule.search) {
+            (Some(id), _) => self
+                .saved_searches
+                .get(id)
+                .map(|saved| saved.request)
+                .ok_or_else(|| format!("Saved search {} not found", id))?,
+            (None, Some(search)) => search.clone(),
+            (None, None) => return Err("Rule has neither saved_search nor search".to_string()),
+        };
+        request["start_time"] = json!(format!("now-{}m", rule.window_minutes));
+        request["end_time"] = json!("now");
+        serde_json::from_value(request).map_err(|e| format!("Invalid search request: {}", e))
+    }
+
+    async fn count(&self, indices: &[String], filters: Vec<Value>) -> Result<u64, String> {
+        let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+        let body = json!({ "query": { "bool": { "filter": filters } } });
+        let response = self.es.send_with_retry(|| self.es
+            .count(CountParts::Index(&indices))
+            .ignore_unavailable(true)
+            .body(body.clone())
+            .send())
+            .await;
+        let body = es_json(response)
+            .await
+            .map_err(|e| format!("Error during count: {}", e))?;
+        body["count"]
+            .as_u64()
+            .ok_or_else(|| format!("Unexpected count response: {}", body))
+    }
+
+    async fn measure(&self, rule: &AlertRule) -> Result<(f64, bool), String> {
+        let request = self.search_request(rule)?;
+        let filters = request.filters().map_err(|e| format!("Invalid query: {}", e))?;
+        let indices = request.indices(&self.routing);
+        let total = self.count(&indices, filters.clone()).await?;
+
+        Ok(match &rule.condition {
+            Condition::Count { op, threshold } => (total as f64, op.matches(total as f64, *threshold)),
+            Condition::Absence => (total as f64, total == 0),
+            Condition::Ratio { numerator_query, op, threshold } => {
+                let mut numerator_filters = filters;
+                numerator_filters.push(compile_query(numerator_query).map_err(|e| format!("Invalid numerator_query: {}", e))?);
+                let matched = self.count(&indices, numerator_filters).await?;
+                let ratio = if total == 0 { 0.0 } else { matched as f64 / total as f64 };
+                (ratio, op.matches(ratio, *threshold))
+            }
+        })
+    }
+
+    // 评估单条规则并在需要时发送通知，返回评估后的状态
+    pub async fn evaluate(&self, rule: &AlertRule) -> AlertState {
+        let _guard = match self.evaluating.get(&rule.name) {
+            Some(lock) => Some(lock.lock().await),
+            None => None,
+        };
+        let now = now_millis();
+        let result = self.measure(rule).await;
+        self.record(rule, result, now).await
+    }
+
+    // 记录评估结果；状态变化在通知送达后才提交
+    async fn record(&self, rule: &AlertRule, result: Result<(f64, bool), String>, now: u64) -> AlertState {
+        let (notify, next) = {
+            let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+            let state = states.entry(rule.name.clone()).or_default();
+            state.last_evaluated = Some(now);
+            let breached = match result {
+                Ok((value, breached)) => {
+                    state.value = Some(value);
+                    state.last_error = None;
+                    Some(breached)
+                }
+                Err(e) => {
+                    info!("Alert rule {} evaluation failed: {}", rule.name, e);
+                    state.last_error = Some(e);
+                    None
+                }
+            };
+            let mut next = state.clone();
+            let notify = breached.and_then(|breached| next.transition(breached, now, rule.repeat_minutes));
+            (notify, next)
+        };
+
+        let Some(status) = notify else {
+            return next;
+        };
+        let payload = json!({
+            "rule": rule.name,
+            "status": status,
+            "value": next.value,
+            "condition": rule.condition,
+            "window_minutes": rule.window_minutes,
+            "since": next.since,
+            "timestamp": now
+        });
+        info!("Alert rule {} is {:?}, value {:?}", rule.name, status, next.value);
+        let mut delivered = rule.webhooks.is_empty();
+        for url in &rule.webhooks {
+            match send_webhook(&self.http, url, &payload).await {
+                Ok(()) => delivered = true,
+                Err(e) => warn!("Failed to notify webhook {} for rule {}: {}", url, rule.name, e),
+            }
+        }
+
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let state = states.entry(rule.name.clone()).or_default();
+        if delivered {
+            *state = AlertState { last_notified: Some(now), ..next };
+        } else {
+            state.last_error = Some(format!("Failed to deliver {:?} notification to any webhook, will retry", status));
+        }
+        state.clone()
+    }
+}
+
+async fn send_webhook(http: &reqwest::Client, url: &str, payload: &Value) -> Result<(), String> {
+    let response = http
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("webhook returned {}", response.status()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn notifies_only_on_state_changes() {
+        let mut state = AlertState::default();
+
+        assert_eq!(state.transition(false, 0, None), None);
+        assert_eq!(state.transition(true, 1_000, None), Some(AlertStatus::Firing));
+        assert_eq!(state.since, Some(1_000));
+        assert_eq!(state.transition(true, 2_000, None), None);
+        assert_eq!(state.transition(false, 3_000, None), Some(AlertStatus::Resolved));
+        assert_eq!(state.transition(false, 4_000, None), None);
+        assert_eq!(state.status, AlertStatus::Resolved);
+        assert_eq!(state.transition(true, 5_000, None), Some(AlertStatus::Firing));
+    }
+
+    #[test]
+    fn repeats_firing_notification_after_interval() {
+        let mut state = AlertState::default();
+        assert_eq!(state.transition(true, 0, Some(1)), Some(AlertStatus::Firing));
+        state.last_notified = Some(0);
+        assert_eq!(state.transition(true, 30_000, Some(1)), None);
+        assert_eq!(state.transition(true, 60_000, Some(1)), Some(AlertStatus::Firing));
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(Comparison::Gt.matches(2.0, 1.0));
+        assert!(!Comparison::Gt.matches(1.0, 1.0));
+        assert!(Comparison::Gte.matches(1.0, 1.0));
+        assert!(Comparison::Lt.matches(0.5, 1.0));
+        assert!(Comparison::Lte.matches(1.0, 1.0));
+    }
+
+    #[test]
+    fn rejects_duplicate_rule_names() {
+        let rule = |name: &str| AlertRule {
+            name: name.to_string(),
+            saved_search: Some("abc".to_string()),
+            search: None,
+            window_minutes: 5,
+            condition: Condition::Absence,
+            webhooks: Vec::new(),
+            repeat_minutes: None,
+        };
+        assert!(check_rule_names(&[rule("a"), rule("b")]).is_ok());
+        assert_eq!(check_rule_names(&[rule("a"), rule("b"), rule("a")]).unwrap_err(), "Duplicate alert rule name: a");
+    }
+
+    // webhook 全部失败时不提交状态变化，下一次评估重新通知
+    #[tokio::test]
+    async fn retries_transition_until_a_webhook_succeeds() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let rule = AlertRule {
+            name: "rtc-errors".to_string(),
+            saved_search: None,
+            search: Some(json!({})),
+            window_minutes: 5,
+            condition: Condition::Count { op: Comparison::Gt, threshold: 0.0 },
+            webhooks: vec![format!("http://{}/hook", closed)],
+            repeat_minutes: None,
+        };
+        let routing = IndexRouting::new(Default::default()).unwrap();
+        let store = SavedSearchStore::open(&std::env::temp_dir().join("alerting-test-missing.json").to_string_lossy()).unwrap();
+        let engine = AlertEngine::new(
+            vec![rule.clone()],
+            60,
+            web::Data::new(EsClient::new(elasticsearch::Elasticsearch::default(), &Default::default())),
+            web::Data::new(store),
+            web::Data::new(routing),
+        );
+
+        let state = engine.record(&rule, Ok((3.0, true)), 1_000).await;
+        assert_eq!(state.status, AlertStatus::Inactive);
+        assert_eq!(state.last_notified, None);
+        assert!(state.last_error.is_some_and(|e| e.contains("will retry")));
+
+        // 没有 webhook 时直接提交
+        let rule = AlertRule { webhooks: Vec::new(), ..rule };
+        let state = engine.record(&rule, Ok((3.0, true)), 2_000).await;
+        assert_eq!((state.status, state.since, state.last_notified), (AlertStatus::Firing, Some(2_000), Some(2_000)));
+        assert_eq!(state.last_error, None);
+    }
+
+    #[tokio::test]
+    async fn delivers_webhook_to_local_stub() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let stub = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 4096];
+            // 读到完整的 JSON 请求体为止
+            while !received.ends_with(b"}") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&received).to_string()
+        });
+
+        let payload = json!({ "rule": "rtc-errors", "status": AlertStatus::Firing });
+        send_webhook(&reqwest::Client::new(), &url, &payload).await.unwrap();
+
+        let request = stub.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request.contains(r#""status":"firing""#));
+    }
+}
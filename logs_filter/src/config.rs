@@ -1,25 +1,194 @@
 use std::env;
 use serde::{Deserialize};
-use serde_yaml;
 use std::fs::File;
 use std::io::Read;
+use crate::alerting::{check_rule_names, AlertRule};
+use crate::index_routing::IndexRoutingConfig;
+use crate::log_parsing::LogParsingConfig;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub(crate) connect_ips: ConnectIps,
+    #[serde(default)]
+    pub(crate) elasticsearch: EsClientConfig,
+    #[serde(default)]
+    pub(crate) admin: AdminConfig,
+    #[serde(default)]
+    pub(crate) storage: StorageConfig,
+    #[serde(default)]
+    pub(crate) alerting: AlertingConfig,
+    #[serde(default)]
+    pub(crate) log_parsing: LogParsingConfig,
+    #[serde(default)]
+    pub(crate) index_template: IndexTemplateConfig,
+    #[serde(default)]
+    pub(crate) index_routing: IndexRoutingConfig,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConnectIps {
-    pub(crate) elasticsearch: String,
+    pub(crate) elasticsearch: EsNodes,
     pub(crate) log_source_edges: Vec<String>,
 }
 
+// 单个地址或多个节点地址（多节点时使用轮询连接池）
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum EsNodes {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl EsNodes {
+    pub fn urls(&self) -> Vec<String> {
+        match self {
+            EsNodes::Single(url) => vec![url.clone()],
+            EsNodes::Multiple(urls) => urls.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EsClientConfig {
+    pub(crate) username: Option<String>,      // Basic 认证
+    pub(crate) password: Option<String>,
+    pub(crate) api_key_id: Option<String>,    // API key 认证：id + api_key，或只配置编码后的 api_key
+    pub(crate) api_key: Option<String>,
+    pub(crate) ca_cert_path: Option<String>,  // 自签名集群的 CA 证书（PEM）
+    #[serde(default)]
+    pub(crate) insecure_skip_verify: bool,    // 跳过证书校验，仅用于测试环境
+    #[serde(default = "default_es_timeout_secs")]
+    pub(crate) timeout_secs: Option<u64>,     // 单次请求超时，设为 null 时不限制
+    #[serde(default = "default_es_retries")]
+    pub(crate) retries: u32,                  // 只读请求的重试次数
+    #[serde(default = "default_es_retry_backoff_ms")]
+    pub(crate) retry_backoff_ms: u64,
+    pub(crate) sniff_interval_secs: Option<u64>, // 多节点时刷新节点列表的间隔
+}
+
+fn default_es_timeout_secs() -> Option<u64> {
+    Some(30)
+}
+
+fn default_es_retries() -> u32 {
+    2
+}
+
+fn default_es_retry_backoff_ms() -> u64 {
+    200
+}
+
+impl Default for EsClientConfig {
+    fn default() -> Self {
+        EsClientConfig {
+            username: None,
+            password: None,
+            api_key_id: None,
+            api_key: None,
+            ca_cert_path: None,
+            insecure_skip_verify: false,
+            timeout_secs: default_es_timeout_secs(),
+            retries: default_es_retries(),
+            retry_backoff_ms: default_es_retry_backoff_ms(),
+            sniff_interval_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AdminConfig {
+    pub(crate) token: Option<String>,          // 管理接口令牌（请求头 X-Admin-Token），未配置时禁用管理接口
+    #[serde(default = "default_managed_index_prefix")]
+    pub(crate) managed_index_prefix: String,   // 允许删除/关闭的索引前缀
+}
+
+fn default_managed_index_prefix() -> String {
+    "jkzy-logs-".to_string()
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        AdminConfig {
+            token: None,
+            managed_index_prefix: default_managed_index_prefix(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default = "default_saved_searches_path")]
+    pub(crate) saved_searches_path: String, // 保存的搜索（JSON 文件）
+}
+
+fn default_saved_searches_path() -> String {
+    "data/saved_searches.json".to_string()
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            saved_searches_path: default_saved_searches_path(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertingConfig {
+    #[serde(default = "default_alert_interval_secs")]
+    pub(crate) interval_secs: u64,  // 规则评估间隔
+    #[serde(default)]
+    pub(crate) rules: Vec<AlertRule>,
+}
+
+fn default_alert_interval_secs() -> u64 {
+    60
+}
+
+impl Default for AlertingConfig {
+    fn default() -> Self {
+        AlertingConfig {
+            interval_secs: default_alert_interval_secs(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct IndexTemplateConfig {
+    #[serde(default = "default_index_template_name")]
+    pub(crate) name: String,                    // 模板名，匹配 admin.managed_index_prefix 下的索引
+    #[serde(default = "default_true")]
+    pub(crate) install_on_startup: bool,        // 启动时安装/升级模板和 log_parsing 的 pipeline
+    pub(crate) number_of_shards: Option<u32>,
+    pub(crate) number_of_replicas: Option<u32>,
+}
+
+fn default_index_template_name() -> String {
+    "jkzy-logs".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for IndexTemplateConfig {
+    fn default() -> Self {
+        IndexTemplateConfig {
+            name: default_index_template_name(),
+            install_on_startup: true,
+            number_of_shards: None,
+            number_of_replicas: None,
+        }
+    }
+}
+
 pub fn read_config() -> Result<Config, Box<dyn std::error::Error>> {
     let file_path = env::var("CONFIG_FILE_PATH").unwrap_or_else(|_| "/Users/hanxiaoqing/log-searching/logs_filter/config/config.yaml".to_string());
     let mut file = File::open(file_path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
     let config: Config = serde_yaml::from_str(&contents)?;
+    check_rule_names(&config.alerting.rules)?;
     Ok(config)
 }
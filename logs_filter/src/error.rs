@@ -0,0 +1,154 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use elasticsearch::http::response::Response;
+use serde_json::{json, Value};
+use std::fmt;
+use crate::query_parser::ParseError;
+
+// 各接口共用的错误类型：转换为对应的 HTTP 状态码和统一的 JSON 错误体
+// {"error": 描述, "kind": 错误类别, 以及 ES 返回错误时的 es_status / es_type / root_cause}
+
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Forbidden(String),
+    NotFound(String),
+    Timeout(String),     // 请求 ES 超时
+    Unavailable(String), // 无法连接 ES，或 ES 响应无法解析
+    Elasticsearch {
+        status: u16,
+        error_type: Option<String>,
+        reason: String,
+        root_cause: Value,
+    },
+    Internal(String),
+}
+
+impl ApiError {
+    fn kind(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Timeout(_) => "timeout",
+            ApiError::Unavailable(_) => "es_unavailable",
+            ApiError::Elasticsearch { .. } => "es_error",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    // 根据 ES 的错误响应构造，error 可能是对象（type/reason/root_cause）或字符串
+    pub fn from_es_body(status: u16, body: &Value) -> ApiError {
+        let error = &body["error"];
+        let reason = error["reason"]
+            .as_str()
+            .or_else(|| error.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Elasticsearch returned status {}", status));
+        ApiError::Elasticsearch {
+            status,
+            error_type: error["type"].as_str().map(str::to_string),
+            reason,
+            root_cause: error.get("root_cause").cloned().unwrap_or(Value::Null),
+        }
+    }
+
+    // JSON 错误体，也用于 SSE 的 error 事件
+    pub fn body(&self) -> Value {
+        let mut body = json!({ "error": self.to_string(), "kind": self.kind() });
+        if let ApiError::Elasticsearch { status, error_type, root_cause, .. } = self {
+            body["es_status"] = json!(status);
+            body["es_type"] = json!(error_type);
+            if !root_cause.is_null() {
+                body["root_cause"] = root_cause.clone();
+            }
+        }
+        body
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Timeout(message)
+            | ApiError::Unavailable(message)
+            | ApiError::Internal(message) => write!(f, "{}", message),
+            ApiError::Elasticsearch { reason, .. } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::Unavailable(_) => StatusCode::BAD_GATEWAY,
+            // 查询语法、索引不存在、限流等 ES 状态原样返回，认证失败和 ES 内部错误视为网关错误
+            ApiError::Elasticsearch { status, .. } => match status {
+                400 => StatusCode::BAD_REQUEST,
+                404 => StatusCode::NOT_FOUND,
+                408 | 504 => StatusCode::GATEWAY_TIMEOUT,
+                429 => StatusCode::TOO_MANY_REQUESTS,
+                503 => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::BAD_GATEWAY,
+            },
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+impl From<elasticsearch::Error> for ApiError {
+    fn from(e: elasticsearch::Error) -> Self {
+        if e.is_timeout() {
+            return ApiError::Timeout(format!("Elasticsearch request timed out: {}", e));
+        }
+        match e.status_code() {
+            Some(status) => ApiError::Elasticsearch {
+                status: status.as_u16(),
+                error_type: None,
+                reason: e.to_string(),
+                root_cause: Value::Null,
+            },
+            None => ApiError::Unavailable(format!("Elasticsearch unreachable: {}", e)),
+        }
+    }
+}
+
+impl From<ParseError> for ApiError {
+    fn from(e: ParseError) -> Self {
+        ApiError::BadRequest(format!("Invalid query: {}", e))
+    }
+}
+
+// 读取 ES 响应体，非 2xx 状态转换为带 root_cause 的错误
+pub async fn es_json(response: Result<Response, elasticsearch::Error>) -> Result<Value, ApiError> {
+    let response = response?;
+    let status = response.status_code();
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|e| ApiError::Unavailable(format!("Failed to parse Elasticsearch response: {}", e)))?;
+    if !status.is_success() {
+        return Err(ApiError::from_es_body(status.as_u16(), &body));
+    }
+    Ok(body)
+}
+
+// 请求体 / 查询参数解析失败时同样返回 JSON 错误体
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|e, _| ApiError::BadRequest(format!("Invalid request body: {}", e)).into())
+}
+
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|e, _| ApiError::BadRequest(format!("Invalid query string: {}", e)).into())
+}
@@ -0,0 +1,166 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+// 搜索结果高亮：
+// - tags 模式：返回带 pre/post 标记的片段（默认沿用 Kibana 的标记）
+// - offsets 模式：返回去掉标记的原文和命中区间 [start, end)，
+//   区间以 UTF-16 码元计，前端可直接用于 String.prototype.slice
+// - none 模式：不请求高亮
+
+const DEFAULT_PRE_TAG: &str = "@kibana-highlighted-field@";
+const DEFAULT_POST_TAG: &str = "@/kibana-highlighted-field@";
+
+// offsets 模式下发送给 ES 的标记，使用私有区字符避免与日志内容冲突
+const OFFSET_PRE_TAG: &str = "\u{E000}";
+const OFFSET_POST_TAG: &str = "\u{E001}";
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightMode {
+    #[default]
+    Tags,
+    Offsets,
+    None,
+}
+
+#[derive(Deserialize, Default)]
+pub struct HighlightOptions {
+    #[serde(default)]
+    pub mode: HighlightMode,
+    pub pre_tag: Option<String>,
+    pub post_tag: Option<String>,
+}
+
+impl HighlightOptions {
+    fn tags(&self) -> (&str, &str) {
+        match self.mode {
+            HighlightMode::Offsets => (OFFSET_PRE_TAG, OFFSET_POST_TAG),
+            _ => (
+                self.pre_tag.as_deref().unwrap_or(DEFAULT_PRE_TAG),
+                self.post_tag.as_deref().unwrap_or(DEFAULT_POST_TAG),
+            ),
+        }
+    }
+
+    // 构造查询体中的 `highlight` 参数，none 模式返回 None
+    pub fn query_param(&self) -> Option<Value> {
+        if self.mode == HighlightMode::None {
+            return None;
+        }
+        let (pre_tag, post_tag) = self.tags();
+        Some(json!({
+            "pre_tags": [pre_tag],
+            "post_tags": [post_tag],
+            "fields": {
+                "*": {}
+            },
+            "fragment_size": 2147483647
+        }))
+    }
+
+    // 将 hit["highlight"] 转换为返回给客户端的结构：字段名 -> 片段列表
+    pub fn map_hit(&self, hit: &Value) -> Map<String, Value> {
+        let mut result = Map::new();
+        let Some(highlight) = hit["highlight"].as_object() else {
+            return result;
+        };
+
+        for (field, fragments) in highlight {
+            let fragments = fragments.as_array().map(Vec::as_slice).unwrap_or_default();
+            let mapped: Vec<Value> = fragments
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|fragment| match self.mode {
+                    HighlightMode::Offsets => {
+                        let (text, ranges) = extract_ranges(fragment);
+                        json!({ "text": text, "ranges": ranges })
+                    }
+                    _ => Value::String(fragment.to_string()),
+                })
+                .collect();
+            result.insert(field.clone(), Value::Array(mapped));
+        }
+        result
+    }
+}
+
+// 去掉 offsets 模式的标记，返回原文和命中区间
+fn extract_ranges(fragment: &str) -> (String, Vec<[usize; 2]>) {
+    let mut text = String::with_capacity(fragment.len());
+    let mut ranges = Vec::new();
+    let mut position = 0; // 当前 UTF-16 偏移
+    let mut start = None;
+
+    for ch in fragment.chars() {
+        match ch {
+            '\u{E000}' => start = Some(position),
+            '\u{E001}' => {
+                if let Some(s) = start.take() {
+                    ranges.push([s, position]);
+                }
+            }
+            _ => {
+                text.push(ch);
+                position += ch.len_utf16();
+            }
+        }
+    }
+    (text, ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(mode: HighlightMode) -> HighlightOptions {
+        HighlightOptions { mode, ..Default::default() }
+    }
+
+    // 按 UTF-16 区间截取，与前端 String.prototype.slice 一致
+    fn slice_utf16(text: &str, [start, end]: [usize; 2]) -> String {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        String::from_utf16(&units[start..end]).unwrap()
+    }
+
+    #[test]
+    fn builds_query_param_per_mode() {
+        let tags = options(HighlightMode::Tags).query_param().unwrap();
+        assert_eq!(tags["pre_tags"], json!([DEFAULT_PRE_TAG]));
+        assert_eq!(tags["post_tags"], json!([DEFAULT_POST_TAG]));
+
+        let custom = HighlightOptions { pre_tag: Some("<em>".to_string()), post_tag: Some("</em>".to_string()), ..Default::default() };
+        assert_eq!(custom.query_param().unwrap()["pre_tags"], json!(["<em>"]));
+
+        // offsets 模式忽略自定义标记
+        let offsets = HighlightOptions { mode: HighlightMode::Offsets, ..custom };
+        assert_eq!(offsets.query_param().unwrap()["pre_tags"], json!(["\u{E000}"]));
+        assert_eq!(offsets.query_param().unwrap()["post_tags"], json!(["\u{E001}"]));
+
+        assert!(options(HighlightMode::None).query_param().is_none());
+    }
+
+    #[test]
+    fn maps_fragments_per_mode() {
+        let hit = json!({ "highlight": { "message": ["connect \u{E000}timeout\u{E001} to db"] } });
+        assert_eq!(options(HighlightMode::Tags).map_hit(&hit)["message"], json!(["connect \u{E000}timeout\u{E001} to db"]));
+        assert_eq!(
+            options(HighlightMode::Offsets).map_hit(&hit)["message"],
+            json!([{ "text": "connect timeout to db", "ranges": [[8, 15]] }])
+        );
+        assert!(options(HighlightMode::Offsets).map_hit(&json!({ "_id": "1" })).is_empty());
+    }
+
+    #[test]
+    fn converts_markers_to_utf16_offsets() {
+        // 中文字符占 1 个 UTF-16 码元，emoji 占 2 个（代理对）
+        let (text, ranges) = extract_ranges("用户\u{E000}登录\u{E001}失败 😀 \u{E000}🔥error\u{E001}!");
+        assert_eq!(text, "用户登录失败 😀 🔥error!");
+        assert_eq!(ranges, vec![[2, 4], [10, 17]]);
+        assert_eq!(slice_utf16(&text, ranges[0]), "登录");
+        assert_eq!(slice_utf16(&text, ranges[1]), "🔥error");
+
+        // 没有闭合的开始标记被忽略
+        assert_eq!(extract_ranges("a\u{E000}b"), ("ab".to_string(), vec![]));
+        assert_eq!(extract_ranges("plain"), ("plain".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,325 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// 服务 -> 索引的路由：索引名模式中的 {date} 按 date_format 替换为日期（UTC，与 Logstash 的 %{+YYYY.MM.dd} 一致）。
+// 搜索类接口不再需要浏览器传入索引名，根据所选服务和时间范围解析出具体的每日索引；
+// 时间范围无法解析、超过 max_daily_indices 天或没有起始时间时退化为通配符（{date} 替换为 *）。
+// 不指定服务时查询默认模式和所有服务的模式。
+
+const DATE_PLACEHOLDER: &str = "{date}";
+
+fn default_pattern() -> String {
+    "jkzy-logs-{date}".to_string()
+}
+
+fn default_date_format() -> String {
+    "%Y.%m.%d".to_string()
+}
+
+fn default_max_daily_indices() -> usize {
+    31
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IndexRoutingConfig {
+    #[serde(default = "default_pattern")]
+    pub(crate) default_pattern: String,            // 未单独配置的服务使用的模式
+    #[serde(default = "default_date_format")]
+    pub(crate) date_format: String,                // {date} 的格式（chrono strftime）
+    #[serde(default)]
+    pub(crate) services: BTreeMap<String, String>, // 服务名 -> 索引名模式
+    #[serde(default = "default_max_daily_indices")]
+    pub(crate) max_daily_indices: usize,           // 超过该天数时使用通配符
+}
+
+impl Default for IndexRoutingConfig {
+    fn default() -> Self {
+        IndexRoutingConfig {
+            default_pattern: default_pattern(),
+            date_format: default_date_format(),
+            services: BTreeMap::new(),
+            max_daily_indices: default_max_daily_indices(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexRouting {
+    config: IndexRoutingConfig,
+}
+
+// 按单位取整到开始时刻（y、M、w、d、h/H、m、s），与 ES 日期计算的 /unit 一致
+fn round_down(time: DateTime<Utc>, unit: &str) -> Option<DateTime<Utc>> {
+    let date = time.date_naive();
+    let start = match unit {
+        "y" => NaiveDate::from_ymd_opt(date.year(), 1, 1)?.and_hms_opt(0, 0, 0)?,
+        "M" => NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?.and_hms_opt(0, 0, 0)?,
+        "w" => (date - Duration::days(date.weekday().num_days_from_monday() as i64)).and_hms_opt(0, 0, 0)?,
+        "d" => date.and_hms_opt(0, 0, 0)?,
+        "h" | "H" => date.and_hms_opt(time.hour(), 0, 0)?,
+        "m" => date.and_hms_opt(time.hour(), time.minute(), 0)?,
+        "s" => date.and_hms_opt(time.hour(), time.minute(), time.second())?,
+        _ => return None,
+    };
+    Some(start.and_utc())
+}
+
+fn add_units(time: DateTime<Utc>, number: i64, unit: &str) -> Option<DateTime<Utc>> {
+    let months = |n: i64| Months::new(n.unsigned_abs() as u32);
+    match unit {
+        "y" if number < 0 => time.checked_sub_months(months(number * 12)),
+        "y" => time.checked_add_months(months(number * 12)),
+        "M" if number < 0 => time.checked_sub_months(months(number)),
+        "M" => time.checked_add_months(months(number)),
+        "w" => Some(time + Duration::weeks(number)),
+        "d" => Some(time + Duration::days(number)),
+        "h" | "H" => Some(time + Duration::hours(number)),
+        "m" => Some(time + Duration::minutes(number)),
+        "s" => Some(time + Duration::seconds(number)),
+        _ => None,
+    }
+}
+
+// 计算 now 之后的日期表达式，如 -1d、+2h、-1y/y；round_up 时 /unit 取整到该单位的最后一毫秒（用于结束时间）
+fn date_math(mut expr: &str, now: DateTime<Utc>, round_up: bool) -> Option<DateTime<Utc>> {
+    let mut time = now;
+    while !expr.is_empty() {
+        let (op, rest) = expr.split_at(1);
+        let unit_at = rest.find(|c: char| !c.is_ascii_digit())?;
+        let (number, rest) = rest.split_at(unit_at);
+        let unit_len = rest.chars().next()?.len_utf8();
+        let (unit, rest) = rest.split_at(unit_len);
+        time = match (op, number.parse::<i64>()) {
+            ("/", _) if number.is_empty() => {
+                let start = round_down(time, unit)?;
+                if round_up { add_units(start, 1, unit)? - Duration::milliseconds(1) } else { start }
+            }
+            ("-", Ok(number)) => add_units(time, -number, unit)?,
+            ("+", Ok(number)) => add_units(time, number, unit)?,
+            _ => return None,
+        };
+        expr = rest;
+    }
+    Some(time)
+}
+
+// 解析 ES 风格的时间：now、now-15m、now-1d/d、now/M、epoch 毫秒、RFC 3339 以及不带时区（按 UTC）的日期时间；
+// 取整（/unit）向下取整，结束时间使用 parse_end_time
+pub fn parse_time(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    parse_time_rounding(value, now, false)
+}
+
+// 与 ES 的 lte 一致，取整时取到该单位的最后一毫秒
+pub fn parse_end_time(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    parse_time_rounding(value, now, true)
+}
+
+fn parse_time_rounding(value: &str, now: DateTime<Utc>, round_up: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Some(expr) = value.strip_prefix("now") {
+        return date_math(expr, now, round_up);
+    }
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return DateTime::from_timestamp_millis(value.parse().ok()?);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(time.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+impl IndexRouting {
+    pub fn new(config: IndexRoutingConfig) -> Result<IndexRouting, String> {
+        let mut probe = String::new();
+        if write!(probe, "{}", Utc::now().format(&config.date_format)).is_err() {
+            return Err(format!("index_routing: invalid date_format '{}'", config.date_format));
+        }
+        let patterns = std::iter::once(("default_pattern", &config.default_pattern))
+            .chain(config.services.iter().map(|(service, pattern)| (service.as_str(), pattern)));
+        for (name, pattern) in patterns {
+            if pattern.trim().is_empty() || pattern.contains([',', ' ']) {
+                return Err(format!("index_routing: invalid index pattern for {}: '{}'", name, pattern));
+            }
+        }
+        Ok(IndexRouting { config })
+    }
+
+    // 服务对应的模式（服务名不区分大小写），不指定服务时返回所有模式
+    pub fn patterns(&self, service: Option<&str>) -> Vec<&str> {
+        match service.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(service) => vec![self
+                .config
+                .services
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(service))
+                .map(|(_, pattern)| pattern.as_str())
+                .unwrap_or(&self.config.default_pattern)],
+            None => {
+                let mut patterns = vec![self.config.default_pattern.as_str()];
+                for pattern in self.config.services.values() {
+                    if !patterns.contains(&pattern.as_str()) {
+                        patterns.push(pattern);
+                    }
+                }
+                patterns
+            }
+        }
+    }
+
+    pub fn services(&self) -> &BTreeMap<String, String> {
+        &self.config.services
+    }
+
+    pub fn resolve(&self, service: Option<&str>, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Vec<String> {
+        let days = start.and_then(|start| {
+            let first = start.date_naive();
+            let last = end.unwrap_or_else(Utc::now).date_naive();
+            let count = (last - first).num_days() + 1;
+            (count >= 1 && count as usize <= self.config.max_daily_indices)
+                .then(|| first.iter_days().take(count as usize).collect::<Vec<_>>())
+        });
+
+        let mut indices: Vec<String> = Vec::new();
+        for pattern in self.patterns(service) {
+            let names: Vec<String> = match (&days, pattern.contains(DATE_PLACEHOLDER)) {
+                (Some(days), true) => days
+                    .iter()
+                    .map(|day| pattern.replace(DATE_PLACEHOLDER, &day.format(&self.config.date_format).to_string()))
+                    .collect(),
+                _ => vec![pattern.replace(DATE_PLACEHOLDER, "*")],
+            };
+            for name in names {
+                if !indices.contains(&name) {
+                    indices.push(name);
+                }
+            }
+        }
+        indices
+    }
+
+    // 使用请求中的服务和时间字符串解析
+    pub fn resolve_request(&self, service: &str, start_time: &str, end_time: &str) -> Vec<String> {
+        self.resolve_request_at(service, start_time, end_time, Utc::now())
+    }
+
+    // 以给定的 now 解析相对时间
+    pub fn resolve_request_at(&self, service: &str, start_time: &str, end_time: &str, now: DateTime<Utc>) -> Vec<String> {
+        let start = parse_time(start_time, now);
+        let end = if end_time.trim().is_empty() { Some(now) } else { parse_end_time(end_time, now) };
+        match (start, end) {
+            (Some(start), Some(end)) => self.resolve(Some(service), Some(start), Some(end)),
+            _ => self.resolve(Some(service), None, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routing() -> IndexRouting {
+        IndexRouting::new(IndexRoutingConfig {
+            services: BTreeMap::from([
+                ("RTC".to_string(), "jkzy-logs-rtc-{date}".to_string()),
+                ("Gateway".to_string(), "gateway-logs".to_string()),
+            ]),
+            max_daily_indices: 3,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_es_time_formats() {
+        let now = at("2024-05-02T10:00:00Z");
+        assert_eq!(parse_time("now", now), Some(now));
+        assert_eq!(parse_time("now-15m", now), Some(at("2024-05-02T09:45:00Z")));
+        assert_eq!(parse_time("now-1d/d", now), Some(at("2024-05-01T00:00:00Z")));
+        assert_eq!(parse_time("1714557600000", now), Some(at("2024-05-01T10:00:00Z")));
+        assert_eq!(parse_time("2024-05-01T18:00:00+08:00", now), Some(at("2024-05-01T10:00:00Z")));
+        assert_eq!(parse_time("2024-05-01T10:00:00.123", now), Some(at("2024-05-01T10:00:00.123Z")));
+        assert_eq!(parse_time("2024-05-01", now), Some(at("2024-05-01T00:00:00Z")));
+        assert_eq!(parse_time("now-1q", now), None);
+        assert_eq!(parse_time("", now), None);
+    }
+
+    #[test]
+    fn applies_date_math_rounding() {
+        // 2024-05-02 是星期四
+        let now = at("2024-05-02T10:20:30.456Z");
+        assert_eq!(parse_time("now/M", now), Some(at("2024-05-01T00:00:00Z")));
+        assert_eq!(parse_time("now-1y/y", now), Some(at("2023-01-01T00:00:00Z")));
+        assert_eq!(parse_time("now/w", now), Some(at("2024-04-29T00:00:00Z")));
+        assert_eq!(parse_time("now/h", now), Some(at("2024-05-02T10:00:00Z")));
+        assert_eq!(parse_time("now-1M", now), Some(at("2024-04-02T10:20:30.456Z")));
+        assert_eq!(parse_time("now-1d+2h/m", now), Some(at("2024-05-01T12:20:00Z")));
+        assert_eq!(parse_end_time("now/d", now), Some(at("2024-05-02T23:59:59.999Z")));
+        assert_eq!(parse_end_time("now-1M/M", now), Some(at("2024-04-30T23:59:59.999Z")));
+        assert_eq!(parse_time("now/q", now), None);
+        assert_eq!(parse_time("now-1d/", now), None);
+
+        // 取整后的范围决定查询哪些每日索引
+        assert_eq!(
+            routing().resolve_request_at("RTC", "now-1d/d", "now/d", now),
+            vec!["jkzy-logs-rtc-2024.05.01", "jkzy-logs-rtc-2024.05.02"]
+        );
+    }
+
+    #[test]
+    fn resolves_daily_indices_per_service() {
+        let routing = routing();
+        let start = Some(at("2024-04-30T23:00:00Z"));
+        let end = Some(at("2024-05-01T01:00:00Z"));
+        assert_eq!(routing.resolve(Some("rtc"), start, end), vec!["jkzy-logs-rtc-2024.04.30", "jkzy-logs-rtc-2024.05.01"]);
+        assert_eq!(routing.resolve(Some("Other"), start, end), vec!["jkzy-logs-2024.04.30", "jkzy-logs-2024.05.01"]);
+        // 没有日期的模式原样使用
+        assert_eq!(routing.resolve(Some("Gateway"), start, end), vec!["gateway-logs"]);
+        assert_eq!(
+            routing.resolve(None, start, end),
+            vec!["jkzy-logs-2024.04.30", "jkzy-logs-2024.05.01", "gateway-logs", "jkzy-logs-rtc-2024.04.30", "jkzy-logs-rtc-2024.05.01"]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_wildcards() {
+        let routing = routing();
+        // 超过 max_daily_indices
+        assert_eq!(
+            routing.resolve(Some("RTC"), Some(at("2024-05-01T00:00:00Z")), Some(at("2024-05-04T00:00:00Z"))),
+            vec!["jkzy-logs-rtc-*"]
+        );
+        // 结束时间早于开始时间、没有开始时间或无法解析
+        assert_eq!(
+            routing.resolve(Some("RTC"), Some(at("2024-05-02T00:00:00Z")), Some(at("2024-05-01T00:00:00Z"))),
+            vec!["jkzy-logs-rtc-*"]
+        );
+        assert_eq!(routing.resolve_request("", "", ""), vec!["jkzy-logs-*", "gateway-logs", "jkzy-logs-rtc-*"]);
+        assert_eq!(routing.resolve_request("RTC", "yesterday", "now"), vec!["jkzy-logs-rtc-*"]);
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let invalid = |config: IndexRoutingConfig| IndexRouting::new(config).unwrap_err();
+        assert!(invalid(IndexRoutingConfig { date_format: "%Q".to_string(), ..Default::default() }).contains("date_format"));
+        let services = BTreeMap::from([("RTC".to_string(), "a,b".to_string())]);
+        assert!(invalid(IndexRoutingConfig { services, ..Default::default() }).contains("RTC"));
+    }
+}
@@ -0,0 +1,422 @@
+use elasticsearch::{
+    indices::{IndicesGetIndexTemplateParts, IndicesGetMappingParts, IndicesPutIndexTemplateParts},
+    ingest::{IngestGetPipelineParts, IngestPutPipelineParts},
+};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use crate::config::{AdminConfig, IndexTemplateConfig};
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::log_parsing::{build_pipeline, LogParser, LogParsingConfig};
+
+// 日志索引的 index template 与 ingest pipeline：保证查询依赖的字段（hostname.keyword、service.keyword、
+// @timestamp、basename 等）在新索引中有确定的映射，新索引默认经过 log_parsing 生成的 pipeline。
+// 模板按 TEMPLATE_VERSION 升级（已安装的版本较低或不存在时安装）；pipeline 与配置生成的内容不一致时覆盖。
+// 现有索引的映射与 EXPECTED_FIELDS 比较，差异只报告不修改（映射无法原地修改，需等待索引滚动或 reindex）。
+
+// 修改 EXPECTED_FIELDS 或模板结构时递增
+pub const TEMPLATE_VERSION: u64 = 1;
+
+// 模板优先级，高于 Filebeat / Logstash 自带的模板
+const TEMPLATE_PRIORITY: u64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    Date,
+    Long,
+    Keyword,
+    Text,
+    TextWithKeyword, // text + keyword 子字段，与 ES 动态映射字符串的结果一致
+}
+
+impl FieldKind {
+    fn es_type(self) -> &'static str {
+        match self {
+            FieldKind::Date => "date",
+            FieldKind::Long => "long",
+            FieldKind::Keyword => "keyword",
+            FieldKind::Text | FieldKind::TextWithKeyword => "text",
+        }
+    }
+
+    fn mapping(self) -> Value {
+        match self {
+            FieldKind::TextWithKeyword => json!({
+                "type": "text",
+                "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+            }),
+            kind => json!({ "type": kind.es_type() }),
+        }
+    }
+}
+
+// 查询接口依赖的字段
+const EXPECTED_FIELDS: &[(&str, FieldKind)] = &[
+    ("@timestamp", FieldKind::Date),
+    ("hostname", FieldKind::TextWithKeyword),
+    ("service", FieldKind::TextWithKeyword),
+    ("basename", FieldKind::TextWithKeyword),
+    ("log_level", FieldKind::TextWithKeyword),
+    ("module", FieldKind::TextWithKeyword),
+    ("thread", FieldKind::TextWithKeyword),
+    ("message", FieldKind::Text),
+    ("event.original", FieldKind::Text),
+    ("log.file.path", FieldKind::TextWithKeyword),
+    ("log.offset", FieldKind::Long),
+    ("tags", FieldKind::Keyword),
+];
+
+// 按点号路径生成嵌套的 properties
+fn properties() -> Value {
+    let mut root = Map::new();
+    for (field, kind) in EXPECTED_FIELDS {
+        let mut properties = &mut root;
+        let mut parts = field.split('.').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                properties.insert(part.to_string(), kind.mapping());
+            } else {
+                properties = properties
+                    .entry(part.to_string())
+                    .or_insert_with(|| json!({ "properties": {} }))["properties"]
+                    .as_object_mut()
+                    .unwrap();
+            }
+        }
+    }
+    Value::Object(root)
+}
+
+pub fn index_pattern(admin: &AdminConfig) -> String {
+    format!("{}*", admin.managed_index_prefix)
+}
+
+// lifecycle_policy 为 PUT /index_admin/retention 设置的 ILM 策略，新建的每日索引据此自动删除
+pub fn build_template(config: &IndexTemplateConfig, admin: &AdminConfig, pipeline_id: &str, lifecycle_policy: Option<&str>) -> Value {
+    let mut settings = json!({ "index.default_pipeline": pipeline_id });
+    if let Some(policy) = lifecycle_policy {
+        settings["index.lifecycle.name"] = json!(policy);
+    }
+    if let Some(shards) = config.number_of_shards {
+        settings["index.number_of_shards"] = json!(shards);
+    }
+    if let Some(replicas) = config.number_of_replicas {
+        settings["index.number_of_replicas"] = json!(replicas);
+    }
+    json!({
+        "index_patterns": [index_pattern(admin)],
+        "priority": TEMPLATE_PRIORITY,
+        "version": TEMPLATE_VERSION,
+        "_meta": { "managed_by": "logs_filter" },
+        "template": {
+            "settings": settings,
+            "mappings": {
+                "dynamic_templates": [{
+                    "strings_as_text_keyword": {
+                        "match_mapping_type": "string",
+                        "mapping": FieldKind::TextWithKeyword.mapping()
+                    }
+                }],
+                "properties": properties()
+            }
+        }
+    })
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FieldDrift {
+    pub field: String,
+    pub problem: &'static str, // missing / type_mismatch / missing_keyword
+    pub expected: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+fn field_mapping<'a>(mappings: &'a Value, field: &str) -> Option<&'a Value> {
+    let mut current = mappings;
+    for part in field.split('.') {
+        current = current["properties"].get(part)?;
+    }
+    Some(current)
+}
+
+// 比较单个索引的映射（GET _mapping 返回的 mappings 部分）与预期字段
+pub fn check_mapping(mappings: &Value) -> Vec<FieldDrift> {
+    let mut drift = Vec::new();
+    for (field, kind) in EXPECTED_FIELDS {
+        let problem = |problem, actual: Option<&str>| FieldDrift {
+            field: field.to_string(),
+            problem,
+            expected: kind.es_type(),
+            actual: actual.map(str::to_string),
+        };
+        let Some(mapping) = field_mapping(mappings, field) else {
+            drift.push(problem("missing", None));
+            continue;
+        };
+        // 没有 type 的是对象字段
+        let actual = mapping["type"].as_str().unwrap_or("object");
+        if actual != kind.es_type() {
+            drift.push(problem("type_mismatch", Some(actual)));
+        } else if *kind == FieldKind::TextWithKeyword && mapping["fields"]["keyword"]["type"] != "keyword" {
+            drift.push(problem("missing_keyword", Some(actual)));
+        }
+    }
+    drift
+}
+
+#[derive(Serialize, Debug)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub action: &'static str, // installed / upgraded / up_to_date / outdated / missing / newer_installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexDrift {
+    pub index: String,
+    pub fields: Vec<FieldDrift>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BootstrapReport {
+    pub pipeline: ComponentStatus,
+    pub template: ComponentStatus,
+    pub indices_checked: usize,
+    pub drift: Vec<IndexDrift>,
+}
+
+// GET 不存在的模板/pipeline 时 ES 返回 404，视为未安装
+fn not_found_as_none(result: Result<Value, ApiError>) -> Result<Option<Value>, ApiError> {
+    match result {
+        Ok(body) => Ok(Some(body)),
+        Err(ApiError::Elasticsearch { status: 404, .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+async fn installed_pipeline(es: &EsClient, id: &str) -> Result<Option<Value>, ApiError> {
+    let ingest = es.ingest();
+    let response = es.send_with_retry(|| ingest.get_pipeline(IngestGetPipelineParts::Id(id)).send()).await;
+    Ok(not_found_as_none(es_json(response).await)?.and_then(|body| body.get(id).cloned()))
+}
+
+async fn installed_template(es: &EsClient, name: &str) -> Result<Option<Value>, ApiError> {
+    let indices = es.indices();
+    let response = es.send_with_retry(|| indices.get_index_template(IndicesGetIndexTemplateParts::Name(name)).send()).await;
+    let Some(body) = not_found_as_none(es_json(response).await)? else {
+        return Ok(None);
+    };
+    Ok(body["index_templates"]
+        .as_array()
+        .and_then(|templates| templates.iter().find(|t| t["name"] == name))
+        .map(|template| template["index_template"].clone()))
+}
+
+// 已安装模板中的 ILM 策略名，GET 返回的 settings 是嵌套形式
+fn lifecycle_policy(template: &Value) -> Option<&str> {
+    let settings = &template["template"]["settings"];
+    settings["index"]["lifecycle"]["name"]
+        .as_str()
+        .or_else(|| settings["index.lifecycle.name"].as_str())
+}
+
+// 现有受管理索引的映射差异
+pub async fn check_indices(es: &EsClient, admin: &AdminConfig) -> Result<(usize, Vec<IndexDrift>), ApiError> {
+    let pattern = index_pattern(admin);
+    let patterns = [pattern.as_str()];
+    let indices = es.indices();
+    let response = es.send_with_retry(|| indices.get_mapping(IndicesGetMappingParts::Index(&patterns)).send()).await;
+    let body = es_json(response).await?;
+    let Some(all) = body.as_object() else {
+        return Ok((0, Vec::new()));
+    };
+    let mut drift: Vec<IndexDrift> = all
+        .iter()
+        .map(|(index, mapping)| IndexDrift { index: index.clone(), fields: check_mapping(&mapping["mappings"]) })
+        .filter(|drift| !drift.fields.is_empty())
+        .collect();
+    drift.sort_by(|a, b| a.index.cmp(&b.index));
+    Ok((all.len(), drift))
+}
+
+// 检查（install 为 false）或安装/升级 pipeline 和模板，force 时无论版本都覆盖，最后检查现有索引的映射
+pub async fn bootstrap(
+    es: &EsClient,
+    config: &IndexTemplateConfig,
+    admin: &AdminConfig,
+    parsing: &LogParsingConfig,
+    install: bool,
+    force: bool,
+) -> Result<BootstrapReport, ApiError> {
+    let rules = parsing.effective_rules();
+    let parser = LogParser::new(&rules).map_err(ApiError::BadRequest)?;
+    let pipeline = build_pipeline(&rules);
+
+    // 先安装 pipeline：模板中的 index.default_pipeline 指向它
+    let current = installed_pipeline(es, &parsing.pipeline_id).await?;
+    let up_to_date = current.as_ref().is_some_and(|current| current["processors"] == pipeline["processors"]);
+    let action = match (&current, up_to_date) {
+        (_, true) if !force => "up_to_date",
+        (None, _) if !install => "missing",
+        (Some(_), _) if !install => "outdated",
+        _ => {
+            let failures = parser.check_samples();
+            if !failures.is_empty() {
+                return Err(ApiError::BadRequest(format!("Sample lines do not parse: {}", failures.join("; "))));
+            }
+            let ingest = es.ingest();
+            let put = es.send_with_retry(|| ingest
+                .put_pipeline(IngestPutPipelineParts::Id(&parsing.pipeline_id))
+                .body(pipeline.clone())
+                .send())
+                .await;
+            es_json(put).await?;
+            if current.is_some() { "upgraded" } else { "installed" }
+        }
+    };
+    let pipeline_status = ComponentStatus {
+        name: parsing.pipeline_id.clone(),
+        action,
+        installed_version: None,
+        expected_version: None,
+    };
+
+    let installed_template = installed_template(es, &config.name).await?;
+    let installed = installed_template
+        .as_ref()
+        .map(|template| template["version"].as_u64().unwrap_or(0));
+    let action = match installed {
+        Some(version) if version > TEMPLATE_VERSION && !force => "newer_installed",
+        Some(version) if version == TEMPLATE_VERSION && !force => "up_to_date",
+        None if !install => "missing",
+        Some(_) if !install => "outdated",
+        _ => {
+            let indices = es.indices();
+            // 升级时保留之前设置的保留策略
+            let policy = installed_template.as_ref().and_then(lifecycle_policy);
+            let template = build_template(config, admin, &parsing.pipeline_id, policy);
+            let put = es.send_with_retry(|| indices
+                .put_index_template(IndicesPutIndexTemplateParts::Name(&config.name))
+                .body(template.clone())
+                .send())
+                .await;
+            es_json(put).await?;
+            if installed.is_some() { "upgraded" } else { "installed" }
+        }
+    };
+    let template_status = ComponentStatus {
+        name: config.name.clone(),
+        action,
+        installed_version: if matches!(action, "installed" | "upgraded") { Some(TEMPLATE_VERSION) } else { installed },
+        expected_version: Some(TEMPLATE_VERSION),
+    };
+
+    let (indices_checked, drift) = check_indices(es, admin).await?;
+    Ok(BootstrapReport { pipeline: pipeline_status, template: template_status, indices_checked, drift })
+}
+
+// 把保留策略写入模板，使之后创建的索引也应用该策略
+pub async fn put_template_lifecycle(
+    es: &EsClient,
+    config: &IndexTemplateConfig,
+    admin: &AdminConfig,
+    parsing: &LogParsingConfig,
+    policy: &str,
+) -> Result<(), ApiError> {
+    // 模板的 index.default_pipeline 指向 pipeline，先确保它已安装
+    bootstrap(es, config, admin, parsing, true, false).await?;
+    let indices = es.indices();
+    let template = build_template(config, admin, &parsing.pipeline_id, Some(policy));
+    let put = es.send_with_retry(|| indices
+        .put_index_template(IndicesPutIndexTemplateParts::Name(&config.name))
+        .body(template.clone())
+        .send())
+        .await;
+    es_json(put).await?;
+    Ok(())
+}
+
+// 启动时在后台安装/升级，ES 不可用时只记录日志，不影响服务启动
+pub fn start(es: actix_web::web::Data<EsClient>, config: IndexTemplateConfig, admin: AdminConfig, parsing: LogParsingConfig) {
+    if !config.install_on_startup {
+        return;
+    }
+    tokio::spawn(async move {
+        match bootstrap(&es, &config, &admin, &parsing, true, false).await {
+            Ok(report) => {
+                info!(
+                    "Ingest pipeline {}: {}, index template {}: {}",
+                    report.pipeline.name, report.pipeline.action, report.template.name, report.template.action
+                );
+                for index in &report.drift {
+                    let fields: Vec<String> = index.fields.iter().map(|f| format!("{} ({})", f.field, f.problem)).collect();
+                    warn!("Index {} mapping differs from template: {}", index.index, fields.join(", "));
+                }
+            }
+            Err(e) => warn!("Failed to install index template and ingest pipeline: {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 模板中的映射本身不应有差异
+    #[test]
+    fn template_mappings_match_expected_fields() {
+        let template = build_template(&IndexTemplateConfig::default(), &AdminConfig::default(), "jkzy-logs-parse", None);
+        assert_eq!(template["index_patterns"], json!(["jkzy-logs-*"]));
+        assert_eq!(template["template"]["settings"]["index.default_pipeline"], "jkzy-logs-parse");
+        assert!(template["template"]["settings"].get("index.lifecycle.name").is_none());
+        let mappings = &template["template"]["mappings"];
+        assert_eq!(mappings["properties"]["log"]["properties"]["file"]["properties"]["path"]["fields"]["keyword"]["type"], "keyword");
+        assert!(check_mapping(mappings).is_empty());
+    }
+
+    // 升级模板时从已安装的模板（扁平或嵌套的 settings）中取回保留策略
+    #[test]
+    fn keeps_lifecycle_policy_from_installed_template() {
+        let built = build_template(&IndexTemplateConfig::default(), &AdminConfig::default(), "jkzy-logs-parse", Some("jkzy-logs-retention"));
+        assert_eq!(lifecycle_policy(&built), Some("jkzy-logs-retention"));
+        let installed = json!({
+            "template": { "settings": { "index": { "default_pipeline": "jkzy-logs-parse", "lifecycle": { "name": "custom" } } } }
+        });
+        assert_eq!(lifecycle_policy(&installed), Some("custom"));
+        assert_eq!(lifecycle_policy(&json!({ "template": { "settings": {} } })), None);
+    }
+
+    // 动态映射产生的索引：缺少字段、类型不一致、缺少 keyword 子字段
+    #[test]
+    fn reports_mapping_drift() {
+        let text_keyword = FieldKind::TextWithKeyword.mapping();
+        let mappings = json!({
+            "properties": {
+                "@timestamp": { "type": "date" },
+                "hostname": text_keyword,
+                "service": { "type": "text" },
+                "basename": text_keyword,
+                "log_level": { "type": "keyword" },
+                "module": text_keyword,
+                "thread": text_keyword,
+                "message": { "type": "text" },
+                "event": { "properties": { "original": { "type": "text" } } },
+                "log": { "properties": { "file": { "properties": { "path": text_keyword } }, "offset": { "type": "long" } } }
+            }
+        });
+        let drift = check_mapping(&mappings);
+        assert_eq!(
+            drift,
+            vec![
+                FieldDrift { field: "service".to_string(), problem: "missing_keyword", expected: "text", actual: Some("text".to_string()) },
+                FieldDrift { field: "log_level".to_string(), problem: "type_mismatch", expected: "text", actual: Some("keyword".to_string()) },
+                FieldDrift { field: "tags".to_string(), problem: "missing", expected: "keyword", actual: None },
+            ]
+        );
+    }
+}
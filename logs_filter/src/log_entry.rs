@@ -0,0 +1,144 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use crate::highlight::HighlightOptions;
+
+// 所有搜索接口共用的命中结果映射：统一从 hit 的 `fields` 中读取（`_source` 作为后备），
+// 保证 /search 和 /keyword_search 返回同样结构的 LogEntry。
+
+// 请求参数 fields 中使用该值表示返回全部字段
+pub const ALL_FIELDS: &str = "*";
+
+// LogEntry 固定字段依赖的 ES 字段
+const BASE_FIELDS: &[&str] = &[
+    "@timestamp",
+    "hostname",
+    "service",
+    "basename",
+    "log.file.path",
+    "log.offset",
+    "message",
+    "event.original",
+    "log_level",
+];
+
+#[derive(Serialize, Default)]
+pub struct LogEntry {
+    #[serde(rename = "_index")]
+    pub index: String,
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub timestamp: String,
+    pub hostname: String,
+    pub service: String,
+    pub file_name: String, // 日志文件完整路径（log.file.path）
+    pub basename: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>, // 行在日志文件中的字节偏移（log.offset）
+    pub log_level: String,
+    pub message: String,
+    // 请求中额外指定的字段（或全部字段）
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+    // 高亮片段：字段名 -> 片段列表
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub highlight: Map<String, Value>,
+}
+
+// 构造查询体中的 `fields` 参数
+pub fn fields_param(requested: &[String]) -> Value {
+    if requested.iter().any(|f| f == ALL_FIELDS) {
+        return json!([
+            { "field": "*", "include_unmapped": true },
+            { "field": "@timestamp", "format": "strict_date_optional_time" }
+        ]);
+    }
+
+    let mut fields = vec![json!({ "field": "@timestamp", "format": "strict_date_optional_time" })];
+    fields.extend(
+        BASE_FIELDS
+            .iter()
+            .skip(1)
+            .map(|f| f.to_string())
+            .chain(requested.iter().filter(|f| !BASE_FIELDS.contains(&f.as_str())).cloned())
+            .map(|f| json!({ "field": f, "include_unmapped": true })),
+    );
+    Value::Array(fields)
+}
+
+// 从 hit 中读取单个字段：`fields` 中的值总是数组，取第一个
+fn field_value<'a>(hit: &'a Value, name: &str) -> Option<&'a Value> {
+    match hit["fields"].get(name) {
+        Some(Value::Array(values)) => values.first(),
+        Some(value) => Some(value),
+        None => name
+            .split('.')
+            .try_fold(&hit["_source"], |v, key| v.get(key))
+            .or_else(|| hit["_source"].get(name)),
+    }
+}
+
+fn field_str(hit: &Value, names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| field_value(hit, name).and_then(Value::as_str))
+        .unwrap_or_default()
+        .to_owned()
+}
+
+impl LogEntry {
+    pub fn from_hit(hit: &Value, requested: &[String], highlight: &HighlightOptions) -> LogEntry {
+        let file_name = field_str(hit, &["log.file.path"]);
+        let mut basename = field_str(hit, &["basename"]);
+        if basename.is_empty() {
+            basename = file_name.rsplit('/').next().unwrap_or_default().to_owned();
+        }
+
+        let mut fields = Map::new();
+        if requested.iter().any(|f| f == ALL_FIELDS) {
+            if let Some(all) = hit["fields"].as_object() {
+                for (name, values) in all {
+                    fields.insert(name.clone(), unwrap_single(values));
+                }
+            }
+        } else {
+            for name in requested.iter().filter(|f| !BASE_FIELDS.contains(&f.as_str())) {
+                if let Some(value) = hit["fields"].get(name.as_str()) {
+                    fields.insert(name.clone(), unwrap_single(value));
+                } else if let Some(value) = field_value(hit, name) {
+                    fields.insert(name.clone(), value.clone());
+                }
+            }
+        }
+
+        LogEntry {
+            index: hit["_index"].as_str().unwrap_or_default().to_owned(),
+            id: hit["_id"].as_str().unwrap_or_default().to_owned(),
+            timestamp: field_str(hit, &["@timestamp"]),
+            hostname: field_str(hit, &["hostname"]),
+            service: field_str(hit, &["service"]),
+            file_name,
+            basename,
+            offset: field_value(hit, "log.offset").and_then(Value::as_u64),
+            log_level: field_str(hit, &["log_level"]),
+            message: field_str(hit, &["message", "event.original"]),
+            fields,
+            highlight: highlight.map_hit(hit),
+        }
+    }
+}
+
+// 单值数组展开为标量，便于前端直接使用
+fn unwrap_single(values: &Value) -> Value {
+    match values.as_array() {
+        Some(arr) if arr.len() == 1 => arr[0].clone(),
+        _ => values.clone(),
+    }
+}
+
+// 将 ES 搜索响应中的所有命中映射为 LogEntry
+pub fn map_hits(body: &Value, requested: &[String], highlight: &HighlightOptions) -> Vec<LogEntry> {
+    body["hits"]["hits"]
+        .as_array()
+        .map(|hits| hits.iter().map(|hit| LogEntry::from_hit(hit, requested, highlight)).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,370 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// 日志行解析：按服务配置的正则从原始日志行中提取 timestamp / level / module / thread / message，
+// 生成 ES ingest pipeline（grok + date 处理器）供 Logstash 写入时使用，
+// 同一套规则在 Rust 中编译，用于校验配置中的样例行和 /log_parsing/preview 预览。
+// 正则使用 Rust regex 与 ES（Oniguruma）共同支持的语法，命名分组写作 (?<name>...)。
+
+// 规则中可以使用的命名分组
+pub const FIELDS: &[&str] = &["timestamp", "level", "module", "thread", "message"];
+
+// 匹配所有服务的规则
+const ANY_SERVICE: &str = "*";
+
+// 通用格式：2024-05-01 12:00:00.123 [INFO] [thread] module: message，方括号、线程和模块均可省略
+const DEFAULT_PATTERN: &str = r"^\[?(?<timestamp>\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)\]?\s+\[?(?<level>TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|FATAL|CRITICAL)\]?\s+(?:\[(?<thread>[^\]]+)\]\s+)?(?:(?<module>[\w.$/-]+):\s+)?(?<message>.*)$";
+
+fn default_timestamp_formats() -> Vec<String> {
+    [
+        "yyyy-MM-dd HH:mm:ss.SSS",
+        "yyyy-MM-dd HH:mm:ss,SSS",
+        "yyyy-MM-dd'T'HH:mm:ss.SSS",
+        "yyyy-MM-dd HH:mm:ss",
+        "ISO8601",
+    ]
+    .iter()
+    .map(|f| f.to_string())
+    .collect()
+}
+
+fn default_pipeline_id() -> String {
+    "jkzy-logs-parse".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ParseRule {
+    pub service: String,                 // 服务名（与 Filebeat 写入的 service 字段一致），"*" 表示所有服务
+    pub pattern: String,
+    #[serde(default = "default_timestamp_formats")]
+    pub timestamp_formats: Vec<String>,  // ES date 处理器的时间格式（Java 格式）
+    pub timezone: Option<String>,        // 日志时间所在时区，如 Asia/Shanghai 或 +08:00
+    #[serde(default)]
+    pub samples: Vec<String>,            // 样例行，安装 pipeline 前必须全部能被解析
+}
+
+impl ParseRule {
+    fn default_rule() -> ParseRule {
+        ParseRule {
+            service: ANY_SERVICE.to_string(),
+            pattern: DEFAULT_PATTERN.to_string(),
+            timestamp_formats: default_timestamp_formats(),
+            timezone: None,
+            samples: Vec::new(),
+        }
+    }
+
+    fn matches_service(&self, service: &str) -> bool {
+        self.service == ANY_SERVICE || self.service == service
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LogParsingConfig {
+    #[serde(default = "default_pipeline_id")]
+    pub(crate) pipeline_id: String,
+    #[serde(default)]
+    pub(crate) rules: Vec<ParseRule>, // 按顺序匹配服务，第一条匹配的规则生效；为空时使用通用规则
+}
+
+impl Default for LogParsingConfig {
+    fn default() -> Self {
+        LogParsingConfig {
+            pipeline_id: default_pipeline_id(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl LogParsingConfig {
+    pub fn effective_rules(&self) -> Vec<ParseRule> {
+        if self.rules.is_empty() {
+            vec![ParseRule::default_rule()]
+        } else {
+            self.rules.clone()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Default, PartialEq)]
+pub struct ParsedLine {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: ParseRule,
+    regex: Regex,
+}
+
+#[derive(Debug)]
+pub struct LogParser {
+    rules: Vec<CompiledRule>,
+}
+
+impl LogParser {
+    // 编译规则：正则必须有效、只使用已知的命名分组并包含 message 分组
+    pub fn new(rules: &[ParseRule]) -> Result<LogParser, String> {
+        let mut compiled = Vec::new();
+        for rule in rules {
+            let regex = Regex::new(&rule.pattern).map_err(|e| format!("rule {}: invalid pattern: {}", rule.service, e))?;
+            if let Some(unknown) = regex.capture_names().flatten().find(|name| !FIELDS.contains(name)) {
+                return Err(format!(
+                    "rule {}: unknown capture group '{}', expected one of {}",
+                    rule.service,
+                    unknown,
+                    FIELDS.join(", ")
+                ));
+            }
+            if !regex.capture_names().flatten().any(|name| name == "message") {
+                return Err(format!("rule {}: pattern must capture (?<message>...)", rule.service));
+            }
+            compiled.push(CompiledRule { rule: rule.clone(), regex });
+        }
+        Ok(LogParser { rules: compiled })
+    }
+
+    fn rule_for(&self, service: &str) -> Option<&CompiledRule> {
+        self.rules.iter().find(|compiled| compiled.rule.matches_service(service))
+    }
+
+    pub fn parse(&self, service: &str, line: &str) -> Option<ParsedLine> {
+        let captures = self.rule_for(service)?.regex.captures(line)?;
+        let field = |name: &str| captures.name(name).map(|m| m.as_str().to_string());
+        Some(ParsedLine {
+            timestamp: field("timestamp"),
+            level: field("level"),
+            module: field("module"),
+            thread: field("thread"),
+            message: field("message"),
+        })
+    }
+
+    // 逐条规则检查样例行，返回所有无法解析的样例
+    pub fn check_samples(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .flat_map(|compiled| {
+                compiled
+                    .rule
+                    .samples
+                    .iter()
+                    .filter(|sample| !compiled.regex.is_match(sample))
+                    .map(|sample| format!("rule {}: sample does not match: {}", compiled.rule.service, sample))
+            })
+            .collect()
+    }
+}
+
+// Painless 字符串字面量
+fn painless_str(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// 每条规则在 pipeline 中的生效条件：服务匹配，且没有被前面的规则匹配；永远不会生效的规则返回 None
+fn rule_conditions(rules: &[ParseRule]) -> Vec<Option<Option<String>>> {
+    rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let earlier = &rules[..i];
+            if earlier.iter().any(|r| r.service == ANY_SERVICE || r.service == rule.service) {
+                return None;
+            }
+            if rule.service != ANY_SERVICE {
+                return Some(Some(format!("ctx.service == {}", painless_str(&rule.service))));
+            }
+            if earlier.is_empty() {
+                return Some(None);
+            }
+            let mut services: Vec<String> = Vec::new();
+            for service in earlier.iter().map(|r| painless_str(&r.service)) {
+                if !services.contains(&service) {
+                    services.push(service);
+                }
+            }
+            Some(Some(format!("!([{}].contains(ctx.service))", services.join(", "))))
+        })
+        .collect()
+}
+
+fn with_condition(mut processor: Value, condition: &Option<String>) -> Value {
+    if let Some(condition) = condition {
+        let (_, body) = processor.as_object_mut().unwrap().iter_mut().next().unwrap();
+        body["if"] = json!(condition);
+    }
+    processor
+}
+
+// 生成 ingest pipeline：原始行保存在 event.original，解析出的 message 覆盖 message，
+// level 写入 log_level，timestamp 经 date 处理器写入 @timestamp；同时从 log.file.path 提取 basename。
+// 解析失败的文档保留原始内容并打上 _parse_failure 标签。
+pub fn build_pipeline(rules: &[ParseRule]) -> Value {
+    let mut processors = vec![
+        json!({ "set": { "field": "event.original", "copy_from": "message", "override": false, "ignore_empty_value": true } }),
+        json!({ "script": {
+            "if": "ctx.log?.file?.path != null",
+            "source": "def path = ctx.log.file.path; ctx.basename = path.substring(path.lastIndexOf('/') + 1);"
+        } }),
+    ];
+
+    for (rule, condition) in rules.iter().zip(rule_conditions(rules)) {
+        let Some(condition) = condition else {
+            continue;
+        };
+        processors.push(with_condition(
+            json!({ "grok": {
+                "tag": format!("parse-{}", rule.service),
+                "field": "event.original",
+                "patterns": [rule.pattern],
+                "on_failure": [{ "append": { "field": "tags", "value": "_parse_failure" } }]
+            } }),
+            &condition,
+        ));
+        let mut date = json!({ "date": {
+            "tag": format!("timestamp-{}", rule.service),
+            "field": "timestamp",
+            "target_field": "@timestamp",
+            "formats": rule.timestamp_formats,
+            "on_failure": [{ "append": { "field": "tags", "value": "_timestamp_failure" } }]
+        } });
+        if let Some(timezone) = &rule.timezone {
+            date["date"]["timezone"] = json!(timezone);
+        }
+        let date_condition = Some(match &condition {
+            Some(condition) => format!("({}) && ctx.timestamp != null", condition),
+            None => "ctx.timestamp != null".to_string(),
+        });
+        processors.push(with_condition(date, &date_condition));
+    }
+
+    processors.push(json!({ "rename": { "field": "level", "target_field": "log_level", "ignore_missing": true, "override": true } }));
+    processors.push(json!({ "remove": { "field": "timestamp", "ignore_missing": true } }));
+
+    json!({
+        "description": "Parse raw log lines into timestamp, log_level, module, thread and message (managed by logs_filter)",
+        "_meta": { "managed_by": "logs_filter" },
+        "processors": processors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(service: &str, pattern: &str, samples: &[&str]) -> ParseRule {
+        ParseRule {
+            service: service.to_string(),
+            pattern: pattern.to_string(),
+            timestamp_formats: default_timestamp_formats(),
+            timezone: None,
+            samples: samples.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn parsed(timestamp: &str, level: &str, module: Option<&str>, thread: Option<&str>, message: &str) -> ParsedLine {
+        ParsedLine {
+            timestamp: Some(timestamp.to_string()),
+            level: Some(level.to_string()),
+            module: module.map(str::to_string),
+            thread: thread.map(str::to_string),
+            message: Some(message.to_string()),
+        }
+    }
+
+    // 通用规则的样例行
+    #[test]
+    fn default_rule_parses_sample_lines() {
+        let parser = LogParser::new(&LogParsingConfig::default().effective_rules()).unwrap();
+        let cases = [
+            (
+                "2024-05-01 12:00:00.123 [INFO] [main] rtc.engine: joined channel 42",
+                parsed("2024-05-01 12:00:00.123", "INFO", Some("rtc.engine"), Some("main"), "joined channel 42"),
+            ),
+            (
+                "2024-05-01T12:00:00,5 ERROR [worker-3] connection reset: peer closed",
+                parsed("2024-05-01T12:00:00,5", "ERROR", None, Some("worker-3"), "connection reset: peer closed"),
+            ),
+            (
+                "[2024-05-01 12:00:00] WARN disk usage above 90%",
+                parsed("2024-05-01 12:00:00", "WARN", None, None, "disk usage above 90%"),
+            ),
+            (
+                "2024-05-01 12:00:00.123456 [DEBUG] sdk/api.cc: enter",
+                parsed("2024-05-01 12:00:00.123456", "DEBUG", Some("sdk/api.cc"), None, "enter"),
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parser.parse("RTC", line), Some(expected), "line: {}", line);
+        }
+        assert_eq!(parser.parse("RTC", "   at com.example.Foo.bar(Foo.java:42)"), None);
+    }
+
+    // 按服务选择规则，第一条匹配的规则生效
+    #[test]
+    fn selects_rule_by_service() {
+        let rules = [
+            rule("RTM", r"^(?<timestamp>\d{2}/\d{2} \d{2}:\d{2}:\d{2}) (?<level>[IWE]) (?<message>.*)$", &["01/02 03:04:05 I hello"]),
+            ParseRule::default_rule(),
+        ];
+        let parser = LogParser::new(&rules).unwrap();
+        assert_eq!(
+            parser.parse("RTM", "01/02 03:04:05 W slow frame"),
+            Some(ParsedLine {
+                timestamp: Some("01/02 03:04:05".to_string()),
+                level: Some("W".to_string()),
+                message: Some("slow frame".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(parser.parse("RTC", "01/02 03:04:05 W slow frame"), None);
+        assert!(parser.parse("RTC", "2024-05-01 12:00:00 INFO ok").is_some());
+        assert!(parser.check_samples().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_rules_and_samples() {
+        assert!(LogParser::new(&[rule("A", r"^(?<msg>.*)$", &[])]).unwrap_err().contains("unknown capture group 'msg'"));
+        assert!(LogParser::new(&[rule("A", r"^(?<level>\w+)", &[])]).unwrap_err().contains("must capture"));
+        assert!(LogParser::new(&[rule("A", r"^(?<message>.*", &[])]).unwrap_err().contains("invalid pattern"));
+
+        let parser = LogParser::new(&[rule("A", r"^INFO (?<message>.*)$", &["INFO ok", "ERROR bad"])]).unwrap();
+        assert_eq!(parser.check_samples(), vec!["rule A: sample does not match: ERROR bad".to_string()]);
+    }
+
+    #[test]
+    fn pipeline_conditions_follow_rule_order() {
+        let rules = [
+            rule("RTC", r"^(?<message>.*)$", &[]),
+            rule("O'Brien", r"^(?<message>.*)$", &[]),
+            rule("RTC", r"^(?<message>.*)$", &[]), // 被第一条覆盖，不会生成
+            ParseRule::default_rule(),
+        ];
+        let pipeline = build_pipeline(&rules);
+        let groks: Vec<&Value> = pipeline["processors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.get("grok"))
+            .collect();
+        assert_eq!(groks.len(), 3);
+        assert_eq!(groks[0]["if"], "ctx.service == 'RTC'");
+        assert_eq!(groks[1]["if"], "ctx.service == 'O\\'Brien'");
+        assert_eq!(groks[2]["if"], "!(['RTC', 'O\\'Brien'].contains(ctx.service))");
+
+        let default_only = build_pipeline(&[ParseRule::default_rule()]);
+        let grok = default_only["processors"].as_array().unwrap().iter().find_map(|p| p.get("grok")).unwrap();
+        assert!(grok.get("if").is_none());
+    }
+}
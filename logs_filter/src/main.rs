@@ -1,46 +1,103 @@
 mod routes;
 mod config;
+mod query_parser;
+mod log_entry;
+mod highlight;
+mod saved_search_store;
+mod alerting;
+mod es_client;
+mod telemetry;
+mod error;
+mod log_parsing;
+mod index_template;
+mod index_routing;
 
 use std::env;
-use crate::env_logger::Env;
-use log::info;
+use env_logger::Env;
+use log::{error, info};
 use actix_web::{web, App, HttpServer};
 use actix_files::Files;
 use actix_cors::Cors;
-use elasticsearch::{Elasticsearch};
-use elasticsearch::http::transport::Transport;
-use routes::{search, unique_services, get_indices, discover_node,keyword_search};
-use env_logger;
+use routes::{search, field_values, get_indices, discover_node, keyword_search, context, histogram, index_admin, export, saved_searches, alerts, live_search, health, metrics, parsing};
 use crate::config::read_config;
+use crate::saved_search_store::SavedSearchStore;
+use crate::alerting::AlertEngine;
+use crate::es_client::build_client;
+use crate::index_routing::IndexRouting;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // 初始化日志记录
     env_logger::Builder::from_env(Env::default().default_filter_or("debug")).init();
 
-    let mut es_ip = String::new();
-    match read_config() {
-        Ok(config) => {
-            es_ip  = config.connect_ips.elasticsearch.clone();
-        }
+    let config = match read_config() {
+        Ok(config) => config,
         Err(e) => {
-            info!("Error reading config: {}", e);
-            return Ok(())
+            // 启动失败时以非零状态退出，便于 docker-compose/k8s 发现问题
+            error!("Error reading config: {}", e);
+            return Err(std::io::Error::other(e.to_string()))
         }
-    }
+    };
 
     info!("Starting Actix Web server...");
-    let transport = Transport::single_node(es_ip.as_str()).unwrap();
-    let es_client = Elasticsearch::new(transport);
+    let es_client = match build_client(&config.connect_ips.elasticsearch.urls(), &config.elasticsearch) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Error creating Elasticsearch client: {}", e);
+            return Err(std::io::Error::other(e.to_string()))
+        }
+    };
     let data_es_client = web::Data::new(es_client);
 
-    let current_dir = env::current_dir().unwrap();
+    let index_routing = match IndexRouting::new(config.index_routing.clone()) {
+        Ok(routing) => web::Data::new(routing),
+        Err(e) => {
+            error!("Error in index routing config: {}", e);
+            return Err(std::io::Error::other(e))
+        }
+    };
+
+    let saved_search_store = match SavedSearchStore::open(&config.storage.saved_searches_path) {
+        Ok(store) => web::Data::new(store),
+        Err(e) => {
+            error!("Error loading saved searches from {}: {}", config.storage.saved_searches_path, e);
+            return Err(std::io::Error::other(e.to_string()))
+        }
+    };
+
+    let alert_engine = web::Data::new(AlertEngine::new(
+        config.alerting.rules.clone(),
+        config.alerting.interval_secs,
+        data_es_client.clone(),
+        saved_search_store.clone(),
+        index_routing.clone(),
+    ));
+    AlertEngine::start(alert_engine.clone());
+
+    index_template::start(
+        data_es_client.clone(),
+        config.index_template.clone(),
+        config.admin.clone(),
+        config.log_parsing.clone(),
+    );
+
+    // 其余配置在启动时读取一次，由各接口共享
+    let app_config = web::Data::new(config);
+
+    let current_dir = env::current_dir()?;
     let build_path = format!("{}/build", current_dir.display());
     let static_path = format!("{}/build/static", current_dir.display());
 
     HttpServer::new(move || {
         App::new()
             .app_data(data_es_client.clone())
+            .app_data(saved_search_store.clone())
+            .app_data(index_routing.clone())
+            .app_data(app_config.clone())
+            .app_data(alert_engine.clone())
+            .app_data(error::json_config())
+            .app_data(error::query_config())
+            .wrap(actix_web::middleware::from_fn(telemetry::track_requests))
             // 添加 CORS 配置
             .wrap(
                 Cors::default()
@@ -56,10 +113,20 @@ async fn main() -> std::io::Result<()> {
             .service(Files::new("/static", &static_path).show_files_listing())
             .service(Files::new("/admin", &build_path).index_file("index.html"))
             .configure(search::init_routes)
-            .configure(unique_services::init_routes)
+            .configure(field_values::init_routes)
             .configure(get_indices::init_routes)
             .configure(discover_node::init_routes)
             .configure(keyword_search::init_routes)
+            .configure(context::init_routes)
+            .configure(histogram::init_routes)
+            .configure(index_admin::init_routes)
+            .configure(export::init_routes)
+            .configure(saved_searches::init_routes)
+            .configure(alerts::init_routes)
+            .configure(live_search::init_routes)
+            .configure(health::init_routes)
+            .configure(metrics::init_routes)
+            .configure(parsing::init_routes)
     })
         .bind("0.0.0.0:8080")?
         .run()
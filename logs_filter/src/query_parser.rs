@@ -0,0 +1,433 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+// 查询语法（类 Lucene/KQL）：
+//   service:RTC AND level:ERROR AND "join channel failed" NOT hostname:test-*
+// - `field:value` 字段匹配，value 可以是单词或双引号短语，含 `*`/`?` 时按通配符匹配
+// - 不带字段的单词/短语匹配 message
+// - AND / OR / NOT（大写）以及括号分组，相邻的两个条件之间默认是 AND
+// 优先级：NOT > AND > OR
+
+// 查询中可使用的字段别名 -> (ES 字段名, 是否有 .keyword 子字段)
+const FIELD_ALIASES: &[(&str, &str, bool)] = &[
+    ("service", "service", true),
+    ("hostname", "hostname", true),
+    ("host", "hostname", true),
+    ("basename", "basename", true),
+    ("file", "basename", true),
+    ("level", "log_level", true),
+    ("log_level", "log_level", true),
+    ("message", "message", false),
+    ("msg", "message", false),
+];
+
+const DEFAULT_FIELD: &str = "message";
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub position: usize, // 出错位置（字符偏移）
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "syntax error at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Colon,
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Term { field: Option<String>, value: String, phrase: bool },
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((Token::LParen, i));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::RParen, i));
+                i += 1;
+            }
+            ':' => {
+                tokens.push((Token::Colon, i));
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                let mut phrase = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => {
+                            return Err(ParseError {
+                                position: start,
+                                message: "unterminated quoted phrase".to_string(),
+                            })
+                        }
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if i + 1 < chars.len() => {
+                            phrase.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&ch) => {
+                            phrase.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push((Token::Phrase(phrase), start));
+            }
+            _ => {
+                let start = i;
+                let mut word = String::new();
+                while let Some(&ch) = chars.get(i) {
+                    if ch.is_whitespace() || matches!(ch, '(' | ')' | ':' | '"') {
+                        break;
+                    }
+                    if ch == '\\' && i + 1 < chars.len() {
+                        word.push(chars[i + 1]);
+                        i += 2;
+                        continue;
+                    }
+                    word.push(ch);
+                    i += 1;
+                }
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                };
+                tokens.push((token, start));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize, // 输入长度，用于报告“意外结束”的位置
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map(|(_, p)| *p).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position(),
+            message: message.into(),
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            items.push(self.parse_and()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::Or(items) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.parse_not()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    items.push(self.parse_not()?);
+                }
+                // 隐式 AND：两个条件直接相邻
+                Some(Token::Word(_)) | Some(Token::Phrase(_)) | Some(Token::Not) | Some(Token::LParen) => {
+                    items.push(self.parse_not()?);
+                }
+                _ => break,
+            }
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { Expr::And(items) })
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::LParen) => {
+                self.next();
+                if self.peek() == Some(&Token::RParen) {
+                    return self.error("empty group '()'");
+                }
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return self.error("expected ')'");
+                }
+                self.next();
+                Ok(expr)
+            }
+            Some(Token::Word(word)) => {
+                self.next();
+                if self.peek() == Some(&Token::Colon) {
+                    self.next();
+                    return self.parse_field_value(word);
+                }
+                Ok(Expr::Term { field: None, value: word, phrase: false })
+            }
+            Some(Token::Phrase(phrase)) => {
+                self.next();
+                Ok(Expr::Term { field: None, value: phrase, phrase: true })
+            }
+            Some(Token::RParen) => self.error("unexpected ')'"),
+            Some(Token::Colon) => self.error("missing field name before ':'"),
+            Some(Token::And) | Some(Token::Or) => self.error("operator is missing its left operand"),
+            Some(Token::Not) => self.error("unexpected NOT"),
+            None => self.error("unexpected end of query"),
+        }
+    }
+
+    fn parse_field_value(&mut self, field: String) -> Result<Expr, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Word(value)) => {
+                self.next();
+                Ok(Expr::Term { field: Some(field), value, phrase: false })
+            }
+            Some(Token::Phrase(value)) => {
+                self.next();
+                Ok(Expr::Term { field: Some(field), value, phrase: true })
+            }
+            _ => self.error(format!("missing value for field '{}'", field)),
+        }
+    }
+}
+
+fn parse(input: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err(ParseError { position: 0, message: "empty query".to_string() });
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.chars().count(),
+    };
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(Token::RParen) => parser.error("unexpected ')'"),
+        Some(Token::Colon) => parser.error("unexpected ':'"),
+        Some(_) => parser.error("unexpected token"),
+    }
+}
+
+fn resolve_field(field: &str) -> (String, bool) {
+    FIELD_ALIASES
+        .iter()
+        .find(|(alias, _, _)| *alias == field)
+        .map(|(_, name, keyword)| (name.to_string(), *keyword))
+        .unwrap_or_else(|| (field.to_string(), false))
+}
+
+fn to_dsl(expr: &Expr) -> Value {
+    match expr {
+        Expr::Term { field, value, phrase } => {
+            let (field, has_keyword) = resolve_field(field.as_deref().unwrap_or(DEFAULT_FIELD));
+            let is_wildcard = !phrase && (value.contains('*') || value.contains('?'));
+            if is_wildcard {
+                let target = if has_keyword { format!("{}.keyword", field) } else { field };
+                json!({ "wildcard": { target: { "value": value, "case_insensitive": true } } })
+            } else {
+                json!({ "match_phrase": { field: value } })
+            }
+        }
+        Expr::And(items) => {
+            let mut filter = Vec::new();
+            let mut must_not = Vec::new();
+            for item in items {
+                match item {
+                    Expr::Not(inner) => must_not.push(to_dsl(inner)),
+                    other => filter.push(to_dsl(other)),
+                }
+            }
+            json!({ "bool": { "filter": filter, "must_not": must_not } })
+        }
+        Expr::Or(items) => {
+            let should: Vec<Value> = items.iter().map(to_dsl).collect();
+            json!({ "bool": { "should": should, "minimum_should_match": 1 } })
+        }
+        Expr::Not(inner) => json!({ "bool": { "must_not": [to_dsl(inner)] } }),
+    }
+}
+
+// 将查询表达式编译为 Elasticsearch bool 查询
+pub fn compile_query(input: &str) -> Result<Value, ParseError> {
+    parse(input).map(|expr| to_dsl(&expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: Option<&str>, value: &str, phrase: bool) -> Expr {
+        Expr::Term {
+            field: field.map(str::to_string),
+            value: value.to_string(),
+            phrase,
+        }
+    }
+
+    #[test]
+    fn parses_field_terms_and_phrases() {
+        assert_eq!(parse("service:RTC").unwrap(), term(Some("service"), "RTC", false));
+        assert_eq!(
+            parse("message:\"join channel\"").unwrap(),
+            term(Some("message"), "join channel", true)
+        );
+        assert_eq!(parse("\"a \\\"b\\\"\"").unwrap(), term(None, "a \"b\"", true));
+    }
+
+    #[test]
+    fn implicit_and_and_precedence() {
+        assert_eq!(
+            parse("a b OR c").unwrap(),
+            Expr::Or(vec![
+                Expr::And(vec![term(None, "a", false), term(None, "b", false)]),
+                term(None, "c", false),
+            ])
+        );
+        assert_eq!(
+            parse("a AND (b OR c)").unwrap(),
+            Expr::And(vec![
+                term(None, "a", false),
+                Expr::Or(vec![term(None, "b", false), term(None, "c", false)]),
+            ])
+        );
+        assert_eq!(
+            parse("NOT NOT a").unwrap(),
+            Expr::Not(Box::new(Expr::Not(Box::new(term(None, "a", false)))))
+        );
+    }
+
+    #[test]
+    fn compiles_example_query() {
+        let dsl = compile_query(
+            "service:RTC AND level:ERROR AND \"join channel failed\" NOT hostname:test-*",
+        )
+        .unwrap();
+        assert_eq!(
+            dsl,
+            json!({
+                "bool": {
+                    "filter": [
+                        { "match_phrase": { "service": "RTC" } },
+                        { "match_phrase": { "log_level": "ERROR" } },
+                        { "match_phrase": { "message": "join channel failed" } }
+                    ],
+                    "must_not": [
+                        { "wildcard": { "hostname.keyword": { "value": "test-*", "case_insensitive": true } } }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn compiles_or_and_standalone_not() {
+        assert_eq!(
+            compile_query("level:ERROR OR level:WARN").unwrap(),
+            json!({
+                "bool": {
+                    "should": [
+                        { "match_phrase": { "log_level": "ERROR" } },
+                        { "match_phrase": { "log_level": "WARN" } }
+                    ],
+                    "minimum_should_match": 1
+                }
+            })
+        );
+        assert_eq!(
+            compile_query("NOT timeout").unwrap(),
+            json!({ "bool": { "must_not": [{ "match_phrase": { "message": "timeout" } }] } })
+        );
+    }
+
+    #[test]
+    fn quoted_wildcards_are_literal() {
+        assert_eq!(
+            compile_query("\"a*b\"").unwrap(),
+            json!({ "match_phrase": { "message": "a*b" } })
+        );
+        assert_eq!(
+            compile_query("uid:12*").unwrap(),
+            json!({ "wildcard": { "uid": { "value": "12*", "case_insensitive": true } } })
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_position() {
+        let err = |q: &str| parse(q).unwrap_err();
+
+        assert_eq!(err("").message, "empty query");
+        assert_eq!(err("\"abc").position, 0);
+        assert_eq!(err("\"abc").message, "unterminated quoted phrase");
+        assert_eq!(err("service:").message, "missing value for field 'service'");
+        assert_eq!(err("a AND").position, 5);
+        assert_eq!(err("a AND").message, "unexpected end of query");
+        assert_eq!(err("(a OR b").message, "expected ')'");
+        assert_eq!(err("a)").message, "unexpected ')'");
+        assert_eq!(err("a:b:c").message, "unexpected ':'");
+        assert_eq!(err("OR a").message, "operator is missing its left operand");
+        assert_eq!(err(":a").message, "missing field name before ':'");
+        assert_eq!(err("()").message, "empty group '()'");
+        assert_eq!(
+            err("a AND").to_string(),
+            "syntax error at position 5: unexpected end of query"
+        );
+    }
+}
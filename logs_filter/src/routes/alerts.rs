@@ -0,0 +1,36 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde_json::{json, Value};
+use crate::alerting::AlertEngine;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::routes::index_admin::authorize;
+
+// 列出所有告警规则及其当前状态
+pub async fn list_alerts(engine: web::Data<AlertEngine>) -> impl Responder {
+    let alerts: Vec<Value> = engine
+        .rules()
+        .iter()
+        .map(|rule| json!({ "rule": rule, "state": engine.state(&rule.name) }))
+        .collect();
+    web::Json(json!({ "alerts": alerts }))
+}
+
+// 立即评估一条规则（会按状态变化发送通知），需要管理令牌
+pub async fn evaluate_alert(
+    req: HttpRequest,
+    engine: web::Data<AlertEngine>,
+    config: web::Data<Config>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&req, &config.admin)?;
+    let Some(rule) = engine.rules().iter().find(|r| r.name == *name) else {
+        return Err(ApiError::NotFound(format!("Alert rule {} not found", name)));
+    };
+    let state = engine.evaluate(rule).await;
+    Ok(HttpResponse::Ok().json(json!({ "rule": rule, "state": state })))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/alerts").route(web::get().to(list_alerts)));
+    cfg.service(web::resource("/alerts/{name}/evaluate").route(web::post().to(evaluate_alert)));
+}
@@ -0,0 +1,125 @@
+use actix_web::web;
+use chrono::{Duration, Utc};
+use elasticsearch::SearchParts;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::highlight::HighlightOptions;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::{parse_time, IndexRouting};
+use crate::log_entry::{fields_param, map_hits, LogEntry};
+
+const DEFAULT_CONTEXT_LINES: usize = 20;
+const MAX_CONTEXT_LINES: usize = 500;
+
+#[derive(Deserialize)]
+pub struct ContextParams {
+    index: String,              // 命中结果所在索引（_index）
+    id: String,                 // 命中结果的 _id
+    es_index: Option<String>,   // 查找上下文的索引模式，默认为命中结果所在服务前后各一天的索引
+    before: Option<usize>,      // 之前的行数
+    after: Option<usize>,       // 之后的行数
+}
+
+// 按时间戳和文件偏移排序，偏移缺失时按 0 处理，保证 search_after 的取值在两个方向上一致
+fn sort_clause(order: &str) -> Value {
+    json!([
+        { "@timestamp": { "order": order, "format": "strict_date_optional_time_nanos" } },
+        { "log.offset": { "order": order, "unmapped_type": "long", "missing": 0 } }
+    ])
+}
+
+async fn run_search(es: &EsClient, indices: &[&str], body: Value) -> Result<Value, ApiError> {
+    let response = es.send_with_retry(|| es
+        .search(SearchParts::Index(indices))
+        .ignore_unavailable(true)
+        .body(body.clone())
+        .send())
+        .await;
+    es_json(response).await
+}
+
+pub async fn get_context(
+    params: web::Query<ContextParams>,
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<web::Json<Value>, ApiError> {
+    let before = params.before.unwrap_or(DEFAULT_CONTEXT_LINES).min(MAX_CONTEXT_LINES);
+    let after = params.after.unwrap_or(DEFAULT_CONTEXT_LINES).min(MAX_CONTEXT_LINES);
+    let no_highlight = HighlightOptions::default();
+
+    // 先取出命中的文档本身，拿到主机名、文件路径以及排序值
+    let anchor_query = json!({
+        "size": 1,
+        "_source": false,
+        "fields": fields_param(&[]),
+        "sort": sort_clause("asc"),
+        "query": { "ids": { "values": [params.id] } }
+    });
+    let anchor_body = run_search(&es, &[params.index.as_str()], anchor_query).await?;
+    let Some(anchor_hit) = anchor_body["hits"]["hits"].get(0) else {
+        return Err(ApiError::NotFound(format!("Document {} not found in {}", params.id, params.index)));
+    };
+    let anchor = LogEntry::from_hit(anchor_hit, &[], &no_highlight);
+    let sort_values = anchor_hit["sort"].clone();
+
+    // 只在同一主机、同一文件内查找，按 keyword 子字段精确匹配
+    let mut filters = Vec::new();
+    if !anchor.hostname.is_empty() {
+        filters.push(json!({ "term": { "hostname.keyword": anchor.hostname } }));
+    }
+    if !anchor.file_name.is_empty() {
+        filters.push(json!({ "term": { "log.file.path.keyword": anchor.file_name } }));
+    } else if !anchor.basename.is_empty() {
+        filters.push(json!({ "term": { "basename.keyword": anchor.basename } }));
+    }
+
+    // 同一文件的上下文可能跨天写入相邻的索引
+    let context_indices = match params.es_index.as_deref().filter(|index| !index.is_empty()) {
+        Some(index) => vec![index.to_string()],
+        None => match parse_time(&anchor.timestamp, Utc::now()) {
+            Some(time) => {
+                let mut indices = routing.resolve(Some(&anchor.service), Some(time - Duration::days(1)), Some(time + Duration::days(1)));
+                if !indices.contains(&params.index) {
+                    indices.push(params.index.clone());
+                }
+                indices
+            }
+            None => vec![params.index.clone()],
+        },
+    };
+    let context_indices: Vec<&str> = context_indices.iter().map(String::as_str).collect();
+    let neighbours = |order: &str, size: usize| {
+        json!({
+            "size": size,
+            "_source": false,
+            "fields": fields_param(&[]),
+            "sort": sort_clause(order),
+            "search_after": sort_values,
+            "query": { "bool": { "filter": filters } }
+        })
+    };
+
+    let mut before_entries = Vec::new();
+    if before > 0 {
+        let body = run_search(&es, &context_indices, neighbours("desc", before)).await?;
+        before_entries = map_hits(&body, &[], &no_highlight);
+        before_entries.reverse(); // 按时间正序返回
+    }
+
+    let mut after_entries = Vec::new();
+    if after > 0 {
+        let body = run_search(&es, &context_indices, neighbours("asc", after)).await?;
+        after_entries = map_hits(&body, &[], &no_highlight);
+    }
+
+    Ok(web::Json(json!({
+        "anchor": anchor,
+        "before": before_entries,
+        "after": after_entries
+    })))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/context").route(web::get().to(get_context)));
+}
@@ -1,5 +1,5 @@
 use futures::future::join_all;
-use actix_web::{web, Responder};
+use actix_web::web;
 use async_tungstenite::tokio::connect_async;
 use async_tungstenite::tungstenite::protocol::Message;
 use futures_util::{SinkExt, StreamExt};
@@ -9,9 +9,11 @@ use tokio::sync::{broadcast, Mutex};
 use std::sync::Arc;
 use tokio::time::{timeout, Duration};
 use crate::config::read_config;
+use crate::error::ApiError;
+use crate::telemetry;
 
 struct WebSocketClient {
-    sender: Arc<Mutex<broadcast::Sender<Value>>>, // Sender 用于发送消息
+    _sender: Arc<Mutex<broadcast::Sender<Value>>>, // Sender 用于发送消息
     receiver: Arc<Mutex<broadcast::Receiver<Value>>>, // Receiver 用于接收消息
 }
 
@@ -62,8 +64,11 @@ impl WebSocketClient {
                 });
 
                 // 每次请求都发送消息
-                write.send(Message::Text(send_text)).await.expect("Failed to send message");
-                Ok(WebSocketClient { sender, receiver })
+                write
+                    .send(Message::Text(send_text))
+                    .await
+                    .map_err(|e| format!("Failed to send message: {}", e))?;
+                Ok(WebSocketClient { _sender: sender, receiver })
             }
             Ok(Err(err)) => {
                 info!("Failed to connect: {}",err);
@@ -78,24 +83,19 @@ impl WebSocketClient {
 
     // 从 broadcast channel 获取日志源数据
     async fn get_log_source(&self) -> Option<Value> {
-        match self.receiver.lock().await.recv().await {
-            Ok(value) => Some(value),
-            Err(_) => None, // 如果接收失败，返回 None
-        }
+        // 如果接收失败，返回 None
+        self.receiver.lock().await.recv().await.ok()
     }
 }
 
-pub async fn discover_node() -> impl Responder {
-    let mut log_ips = vec![];
-    match read_config() {
-        Ok(config) => {
-            log_ips = config.connect_ips.log_source_edges.clone();
-        }
+pub async fn discover_node() -> Result<web::Json<Value>, ApiError> {
+    let log_ips = match read_config() {
+        Ok(config) => config.connect_ips.log_source_edges,
         Err(e) => {
             info!("Error reading config: {}", e);
-            return Err(e);
+            return Err(ApiError::Internal(format!("Error reading config: {}", e)));
         }
-    }
+    };
 
     // 创建一个 `Mutex` 来确保安全地共享合并结果
     let combined_data = Arc::new(Mutex::new(json!({})));
@@ -112,13 +112,16 @@ pub async fn discover_node() -> impl Responder {
                         combined_data_lock[url] = valid_data; // Store the result in combined_data
                     } else {
                         info!("No valid data received for {}", url);
+                        telemetry::edge_discovery_failed(&url, "no_data");
                     }
                 }
                 Ok(Err(e)) => {
                     info!("WebSocketClient::connect failed for {}: {}", url, e);
+                    telemetry::edge_discovery_failed(&url, "connect_failed");
                 }
                 Err(_) => {
                     info!("WebSocketClient::connect timed out for {}", url);
+                    telemetry::edge_discovery_failed(&url, "timeout");
                 }
             }
         })
@@ -0,0 +1,271 @@
+use actix_web::{http::header, web, HttpResponse};
+use elasticsearch::{OpenPointInTimeParts, SearchParts};
+use flate2::{write::GzEncoder, Compression};
+use futures::stream;
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Write;
+use crate::error::{es_json, ApiError};
+use crate::highlight::{HighlightMode, HighlightOptions};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use crate::log_entry::{fields_param, map_hits};
+use crate::routes::search::SearchRequest;
+
+const PAGE_SIZE: usize = 1000;
+const DEFAULT_LIMIT: usize = 100_000;
+const MAX_LIMIT: usize = 1_000_000;
+const PIT_KEEP_ALIVE: &str = "2m";
+const DEFAULT_COLUMNS: &[&str] = &["timestamp", "hostname", "service", "basename", "log_level", "message"];
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+    Raw, // 只输出日志原文，每行一条
+}
+
+#[derive(Deserialize)]
+pub struct ExportRequest {
+    #[serde(flatten)]
+    search: SearchRequest,      // 与 /search 相同的过滤条件
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    columns: Vec<String>,       // CSV 列，默认 DEFAULT_COLUMNS
+    limit: Option<usize>,       // 最多导出的条数
+    #[serde(default)]
+    gzip: bool,                 // 是否 gzip 压缩输出
+}
+
+// 导出结束或客户端断开（响应流被丢弃）时关闭 PIT
+struct PitGuard {
+    es: web::Data<EsClient>,
+    id: String,
+}
+
+impl Drop for PitGuard {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            info!("No runtime to close point in time, it expires after {}", PIT_KEEP_ALIVE);
+            return;
+        };
+        let es = self.es.clone();
+        let id = std::mem::take(&mut self.id);
+        runtime.spawn(async move {
+            let result = es
+                .close_point_in_time()
+                .body(json!({ "id": id }))
+                .send()
+                .await;
+            if let Err(e) = result {
+                info!("Failed to close point in time: {}", e);
+            }
+        });
+    }
+}
+
+// 流式导出的状态：每次从 PIT 中取一页，转换后输出
+struct ExportState {
+    es: web::Data<EsClient>,
+    pit: PitGuard,
+    search_after: Option<Value>,
+    filters: Vec<Value>,
+    fields: Vec<String>,
+    format: ExportFormat,
+    columns: Vec<String>,
+    remaining: usize,
+    header_pending: bool,
+    gzip: Option<GzEncoder<Vec<u8>>>,
+    done: bool,
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_cell(entry: &Value, column: &str) -> String {
+    let value = entry.get(column).or_else(|| entry["fields"].get(column));
+    match value {
+        Some(Value::String(s)) => csv_escape(s),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => csv_escape(&other.to_string()),
+    }
+}
+
+impl ExportState {
+    fn render(&mut self, hits: &Value) -> String {
+        let no_highlight = HighlightOptions { mode: HighlightMode::None, ..Default::default() };
+        let mut out = String::new();
+
+        if self.header_pending && self.format == ExportFormat::Csv {
+            out.push_str(&self.columns.iter().map(|c| csv_escape(c)).collect::<Vec<_>>().join(","));
+            out.push('\n');
+        }
+        self.header_pending = false;
+
+        for entry in map_hits(hits, &self.fields, &no_highlight) {
+            match self.format {
+                ExportFormat::Ndjson => {
+                    out.push_str(&serde_json::to_string(&entry).unwrap_or_default());
+                }
+                ExportFormat::Csv => {
+                    let entry = serde_json::to_value(&entry).unwrap_or_default();
+                    let row: Vec<String> = self.columns.iter().map(|c| csv_cell(&entry, c)).collect();
+                    out.push_str(&row.join(","));
+                }
+                ExportFormat::Raw => out.push_str(entry.message.trim_end_matches(['\r', '\n'])),
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // 压缩（如需要）后得到本次输出的字节
+    fn encode(&mut self, chunk: String, finish: bool) -> std::io::Result<Vec<u8>> {
+        match self.gzip.take() {
+            None => Ok(chunk.into_bytes()),
+            Some(mut encoder) => {
+                encoder.write_all(chunk.as_bytes())?;
+                if finish {
+                    return encoder.finish();
+                }
+                encoder.flush()?;
+                let bytes = std::mem::take(encoder.get_mut());
+                self.gzip = Some(encoder);
+                Ok(bytes)
+            }
+        }
+    }
+
+    async fn next_page(&mut self) -> Result<Value, ApiError> {
+        let mut body = json!({
+            "size": PAGE_SIZE.min(self.remaining),
+            "_source": false,
+            "fields": fields_param(&self.fields),
+            "track_total_hits": false,
+            "pit": { "id": self.pit.id, "keep_alive": PIT_KEEP_ALIVE },
+            "sort": [
+                { "@timestamp": { "order": "desc", "unmapped_type": "boolean" } },
+                { "_shard_doc": "desc" }
+            ],
+            "query": { "bool": { "filter": self.filters } }
+        });
+        if let Some(after) = &self.search_after {
+            body["search_after"] = after.clone();
+        }
+
+        let response = self.es.send_with_retry(|| self.es
+            .search(SearchParts::None)
+            .body(body.clone())
+            .send())
+            .await;
+        let page = es_json(response).await?;
+        if let Some(pit_id) = page["pit_id"].as_str() {
+            self.pit.id = pit_id.to_string();
+        }
+        Ok(page)
+    }
+}
+
+async fn next_chunk(mut state: ExportState) -> Option<(Result<web::Bytes, actix_web::Error>, ExportState)> {
+    if state.done {
+        return None;
+    }
+
+    let page = match state.next_page().await {
+        Ok(page) => page,
+        Err(e) => {
+            state.done = true;
+            return Some((Err(e.into()), state));
+        }
+    };
+
+    let hits = page["hits"]["hits"].as_array().map(Vec::len).unwrap_or_default();
+    state.remaining = state.remaining.saturating_sub(hits);
+    state.search_after = page["hits"]["hits"]
+        .as_array()
+        .and_then(|hits| hits.last())
+        .map(|hit| hit["sort"].clone());
+
+    let finish = hits < PAGE_SIZE || state.remaining == 0;
+    let chunk = state.render(&page);
+    state.done = finish;
+    let bytes = state.encode(chunk, finish).map_err(actix_web::error::ErrorInternalServerError);
+    Some((bytes.map(web::Bytes::from), state))
+}
+
+pub async fn export_logs(
+    request: web::Json<ExportRequest>,
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<HttpResponse, ApiError> {
+    let request = request.into_inner();
+    let filters = request.search.filters()?;
+
+    let indices = request.search.indices(&routing);
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let pit = es.send_with_retry(|| es
+        .open_point_in_time(OpenPointInTimeParts::Index(&indices))
+        .ignore_unavailable(true)
+        .keep_alive(PIT_KEEP_ALIVE)
+        .send())
+        .await;
+    let Some(pit_id) = es_json(pit).await?["id"].as_str().map(str::to_string) else {
+        return Err(ApiError::Unavailable("Failed to open point in time".to_string()));
+    };
+    let pit = PitGuard { es: es.clone(), id: pit_id };
+
+    let columns: Vec<String> = if request.columns.is_empty() {
+        DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect()
+    } else {
+        request.columns.clone()
+    };
+    // CSV 中非 LogEntry 固定字段的列需要额外请求
+    let mut fields = request.search.fields().to_vec();
+    if request.format == ExportFormat::Csv {
+        fields.extend(columns.iter().cloned());
+    }
+
+    let state = ExportState {
+        es,
+        pit,
+        search_after: None,
+        filters,
+        fields,
+        format: request.format,
+        columns,
+        remaining: request.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        header_pending: true,
+        gzip: request.gzip.then(|| GzEncoder::new(Vec::new(), Compression::default())),
+        done: false,
+    };
+
+    let (content_type, extension) = match request.format {
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Raw => ("text/plain; charset=utf-8", "log"),
+    };
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CONTENT_TYPE, content_type));
+    response.insert_header((
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"export.{}\"", extension),
+    ));
+    if request.gzip {
+        response.insert_header((header::CONTENT_ENCODING, "gzip"));
+    }
+    Ok(response.streaming(stream::unfold(state, next_chunk)))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/export").route(web::post().to(export_logs)));
+}
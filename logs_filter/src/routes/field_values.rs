@@ -0,0 +1,135 @@
+use actix_web::web;
+use elasticsearch::SearchParts;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const DEFAULT_SIZE: u32 = 100;
+const MAX_SIZE: u32 = 1000;
+
+// 允许查询取值的字段 -> 聚合使用的 keyword 字段
+const ALLOWED_FIELDS: &[(&str, &str)] = &[
+    ("hostname", "hostname.keyword"),
+    ("service", "service.keyword"),
+    ("basename", "basename.keyword"),
+    ("log_level", "log_level.keyword"),
+];
+
+#[derive(Deserialize)]
+pub struct FieldValuesParams {
+    index_pattern: Option<String>, // 用于指定索引的模式，比如 jkzy-logs-*；省略时按 service 和时间范围解析
+    field: String,              // 字段名，只能是 ALLOWED_FIELDS 中的一个
+    start_time: Option<String>, // 时间范围的开始时间
+    end_time: Option<String>,   // 时间范围的结束时间
+    prefix: Option<String>,     // 输入即搜索：按前缀（不区分大小写）匹配取值
+    hostname: Option<String>,   // 只统计该主机的数据
+    service: Option<String>,    // 只统计该服务的数据
+    size: Option<u32>,          // 最多返回的取值数量
+}
+
+fn keyword_field(name: &str) -> Option<&'static str> {
+    ALLOWED_FIELDS
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, keyword)| *keyword)
+}
+
+pub async fn get_field_values(
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+    params: web::Query<FieldValuesParams>,
+) -> Result<web::Json<Value>, ApiError> {
+    let Some(agg_field) = keyword_field(&params.field) else {
+        let allowed: Vec<&str> = ALLOWED_FIELDS.iter().map(|(field, _)| *field).collect();
+        return Err(ApiError::BadRequest(format!(
+            "Field '{}' is not allowed, expected one of: {}",
+            params.field,
+            allowed.join(", ")
+        )));
+    };
+
+    let mut filters = Vec::new();
+    if params.start_time.is_some() || params.end_time.is_some() {
+        filters.push(json!({
+            "range": {
+                "@timestamp": {
+                    "format": "strict_date_optional_time",
+                    "gte": params.start_time,
+                    "lte": params.end_time
+                }
+            }
+        }));
+    }
+    for (field, value) in [("hostname", &params.hostname), ("service", &params.service)] {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            filters.push(json!({ "match_phrase": { field: value } }));
+        }
+    }
+    if let Some(prefix) = params.prefix.as_deref().filter(|p| !p.is_empty()) {
+        filters.push(json!({
+            "prefix": { agg_field: { "value": prefix, "case_insensitive": true } }
+        }));
+    }
+
+    let query = json!({
+        "size": 0,
+        "query": {
+            "bool": {
+                "filter": filters
+            }
+        },
+        "aggs": {
+            "field_values": {
+                "terms": {
+                    "field": agg_field,
+                    "size": params.size.unwrap_or(DEFAULT_SIZE).clamp(1, MAX_SIZE),
+                    "order": { "_count": "desc" }
+                }
+            }
+        }
+    });
+
+    let indices = match params.index_pattern.as_deref().filter(|pattern| !pattern.is_empty()) {
+        Some(pattern) => vec![pattern.to_string()],
+        None => routing.resolve_request(
+            params.service.as_deref().unwrap_or_default(),
+            params.start_time.as_deref().unwrap_or_default(),
+            params.end_time.as_deref().unwrap_or_default(),
+        ),
+    };
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let response = es.send_with_retry(|| es.search(SearchParts::Index(&indices))
+        .ignore_unavailable(true)
+        .body(query.clone())
+        .send())
+        .await;
+
+    let body = es_json(response).await?;
+    let agg = &body["aggregations"]["field_values"];
+
+    // 每个取值及其文档数
+    let values: Vec<Value> = agg["buckets"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|bucket| {
+            Some(json!({
+                "value": bucket["key"].as_str()?,
+                "count": bucket["doc_count"]
+            }))
+        })
+        .collect();
+
+    Ok(web::Json(json!({
+        "field": params.field,
+        "values": values,
+        "other_count": agg["sum_other_doc_count"]
+    })))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/field_values").route(web::get().to(get_field_values)));
+}
@@ -1,42 +1,163 @@
-use actix_web::{web, Responder};
-use elasticsearch::{Elasticsearch, cat::CatIndicesParts};
-use serde_json::json;
+use actix_web::web;
+use elasticsearch::{cat::CatIndicesParts, indices::IndicesGetMappingParts, params::Bytes};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::config::Config;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
 
-pub async fn get_indices(es: web::Data<Elasticsearch>) -> impl Responder {
+#[derive(Serialize)]
+pub struct IndexInfo {
+    pub index: String,
+    pub health: String,
+    pub status: String,          // open / close
+    pub docs_count: u64,
+    pub store_size_bytes: u64,
+    pub creation_date: u64,      // 创建时间（epoch 毫秒）
+    pub creation_date_string: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<Map<String, Value>>, // 字段名 -> 类型
+}
+
+#[derive(Deserialize)]
+pub struct ResolveParams {
+    #[serde(default)]
+    service: String,    // 服务名，省略时返回所有服务的索引
+    #[serde(default)]
+    start_time: String, // 时间范围，省略开始时间时返回通配符模式
+    #[serde(default)]
+    end_time: String,
+}
+
+#[derive(Deserialize)]
+pub struct IndexStatsParams {
+    pattern: Option<String>,       // 索引模式，默认为受管理的 jkzy-logs-*
+    include_mapping: Option<bool>, // 是否返回每个索引的字段映射摘要
+}
+
+fn parse_u64(entry: &Value, key: &str) -> u64 {
+    entry[key].as_str().and_then(|v| v.parse().ok()).unwrap_or_default()
+}
+
+// 通过 _cat/indices 获取索引的文档数、存储大小、健康状态和创建时间
+pub(crate) async fn list_indices(es: &EsClient, pattern: &str) -> Result<Vec<IndexInfo>, ApiError> {
+    let patterns = [pattern];
+    let cat = es.cat();
+    let response = es.send_with_retry(|| cat.indices(CatIndicesParts::Index(&patterns))
+        .format("json")
+        .bytes(Bytes::B)
+        .h(&["index", "health", "status", "docs.count", "store.size", "creation.date", "creation.date.string"])
+        .s(&["index"])
+        .send())
+        .await;
+    let body = es_json(response).await?;
+
+    Ok(body
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| IndexInfo {
+            index: entry["index"].as_str().unwrap_or_default().to_string(),
+            health: entry["health"].as_str().unwrap_or_default().to_string(),
+            status: entry["status"].as_str().unwrap_or_default().to_string(),
+            docs_count: parse_u64(entry, "docs.count"),
+            store_size_bytes: parse_u64(entry, "store.size"),
+            creation_date: parse_u64(entry, "creation.date"),
+            creation_date_string: entry["creation.date.string"].as_str().unwrap_or_default().to_string(),
+            mapping: None,
+        })
+        .collect())
+}
+
+// 将嵌套的 properties 展开为 "a.b.c" -> type，包括 keyword 等子字段
+fn flatten_mapping(prefix: &str, properties: &Value, out: &mut Map<String, Value>) {
+    let Some(properties) = properties.as_object() else {
+        return;
+    };
+    for (name, definition) in properties {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        let field_type = definition["type"].as_str().unwrap_or("object");
+        out.insert(path.clone(), Value::String(field_type.to_string()));
+        flatten_mapping(&path, &definition["properties"], out);
+        flatten_mapping(&path, &definition["fields"], out);
+    }
+}
+
+pub async fn get_index_stats(
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    params: web::Query<IndexStatsParams>,
+) -> Result<web::Json<Value>, ApiError> {
+    let pattern = match &params.pattern {
+        Some(pattern) => pattern.clone(),
+        None => format!("{}*", config.admin.managed_index_prefix),
+    };
+
+    let mut indices = list_indices(&es, &pattern).await?;
+
+    if params.include_mapping.unwrap_or(false) && !indices.is_empty() {
+        let patterns = [pattern.as_str()];
+        let indices_api = es.indices();
+        let response = es.send_with_retry(|| indices_api
+            .get_mapping(IndicesGetMappingParts::Index(&patterns))
+            .send())
+            .await;
+        let mappings = es_json(response).await?;
+        for info in indices.iter_mut() {
+            let mut fields = Map::new();
+            flatten_mapping("", &mappings[&info.index]["mappings"]["properties"], &mut fields);
+            info.mapping = Some(fields);
+        }
+    }
+
+    let total_docs: u64 = indices.iter().map(|i| i.docs_count).sum();
+    let total_store_bytes: u64 = indices.iter().map(|i| i.store_size_bytes).sum();
+    Ok(web::Json(json!({
+        "indices": indices,
+        "total_docs": total_docs,
+        "total_store_bytes": total_store_bytes
+    })))
+}
+
+pub async fn get_indices(es: web::Data<EsClient>) -> Result<web::Json<Value>, ApiError> {
     // 获取所有索引
-    let response = es.cat().indices(CatIndicesParts::None)
+    let cat = es.cat();
+    let response = es.send_with_retry(|| cat.indices(CatIndicesParts::None)
         .format("json")  // 以 JSON 格式返回
-        .send()
+        .send())
         .await;
 
     //  处理响应
-    match response {
-        Ok(resp) => {
-            let body = resp
-                .json::<serde_json::Value>()
-                .await
-                .unwrap_or_else(|_| json!({"error": "Failed to parse response"}));
-
-            // 提取索引名并组合成数组
-            let indices: Vec<String> = body
-                .as_array()
-                .unwrap_or(&vec![])
-                .iter()
-                .filter_map(|entry| entry.get("index").and_then(|index| index.as_str()))
-                .map(|index| index.to_string())
-                .collect();
-
-            // 返回索引名数组
-            web::Json(json!({ "indices": indices }))
-        }
-        Err(e) => {
-            let error_message = format!("Error indexing logs: {}", e);
-            web::Json(json!({ "error": error_message }))
-        }
-    }
+    let body = es_json(response).await?;
+
+    // 提取索引名并组合成数组
+    let indices: Vec<String> = body
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter_map(|entry| entry.get("index").and_then(|index| index.as_str()))
+        .map(|index| index.to_string())
+        .collect();
+
+    // 返回索引名数组
+    Ok(web::Json(json!({ "indices": indices })))
 }
 
+// 按服务和时间范围解析出的索引，以及配置中单独路由的服务，供前端选择服务
+pub async fn resolve_indices(
+    routing: web::Data<IndexRouting>,
+    params: web::Query<ResolveParams>,
+) -> web::Json<Value> {
+    web::Json(json!({
+        "indices": routing.resolve_request(&params.service, &params.start_time, &params.end_time),
+        "services": routing.services(),
+    }))
+}
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("/get_indices").route(web::get().to(get_indices)));
+    cfg.service(web::resource("/index_stats").route(web::get().to(get_index_stats)));
+    cfg.service(web::resource("/resolve_indices").route(web::get().to(resolve_indices)));
 }
\ No newline at end of file
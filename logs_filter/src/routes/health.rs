@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse};
+use async_tungstenite::tokio::connect_async;
+use async_tungstenite::tungstenite::protocol::Message;
+use elasticsearch::cluster::ClusterHealthParts;
+use futures::future::join_all;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use std::time::Instant;
+use tokio::time::{timeout, Duration};
+use crate::es_client::EsClient;
+use crate::config::Config;
+
+// 存活与就绪探针：/healthz 只表示进程在运行，/readyz 检查 ES 集群状态和边缘节点连通性
+
+const ES_TIMEOUT: Duration = Duration::from_secs(3);
+const EDGE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+// ES 集群为 red 或无法访问时返回错误
+async fn check_elasticsearch(es: &EsClient) -> Result<Value, String> {
+    let cluster = es.cluster();
+    let request = cluster.health(ClusterHealthParts::None).timeout("2s").send();
+    let response = match timeout(ES_TIMEOUT, request).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => return Err(format!("Elasticsearch unreachable: {}", e)),
+        Err(_) => return Err("Elasticsearch health check timed out".to_string()),
+    };
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    let status = body["status"].as_str().unwrap_or("unknown");
+    let summary = json!({
+        "cluster_name": body["cluster_name"],
+        "status": status,
+        "number_of_nodes": body["number_of_nodes"],
+        "unassigned_shards": body["unassigned_shards"],
+    });
+    match status {
+        "green" | "yellow" => Ok(summary),
+        _ => Err(format!("Elasticsearch cluster status is {}", status)),
+    }
+}
+
+// 连接边缘节点并发送 health 命令；旧版本边缘节点不响应该命令时只记录连通性
+async fn check_edge(edge: String) -> Value {
+    let started = Instant::now();
+    let mut ws_stream = match timeout(EDGE_TIMEOUT, connect_async(format!("ws://{}", edge))).await {
+        Ok(Ok((ws_stream, _))) => ws_stream,
+        Ok(Err(e)) => return json!({ "edge": edge, "reachable": false, "error": e.to_string() }),
+        Err(_) => return json!({ "edge": edge, "reachable": false, "error": "connection timed out" }),
+    };
+
+    let mut result = json!({ "edge": edge, "reachable": true });
+    if ws_stream.send(Message::Text("health".to_string())).await.is_ok() {
+        if let Ok(Some(Ok(Message::Text(text)))) = timeout(EDGE_TIMEOUT, ws_stream.next()).await {
+            if let Ok(report) = serde_json::from_str::<Value>(&text) {
+                result["status"] = report["status"].clone();
+                result["report"] = report;
+            }
+        }
+    }
+    result["latency_ms"] = json!(started.elapsed().as_millis() as u64);
+    let _ = ws_stream.close(None).await;
+    result
+}
+
+// ES 可用即为就绪；边缘节点不可达时状态为 degraded，但不影响就绪
+pub async fn readyz(es: web::Data<EsClient>, config: web::Data<Config>) -> HttpResponse {
+    let edges = config.connect_ips.log_source_edges.clone();
+
+    let (elasticsearch, edges) = tokio::join!(
+        check_elasticsearch(&es),
+        join_all(edges.into_iter().map(check_edge))
+    );
+
+    let edges_ok = edges.iter().all(|e| e["reachable"] == true && e["status"] != "degraded");
+    match elasticsearch {
+        Ok(elasticsearch) => HttpResponse::Ok().json(json!({
+            "status": if edges_ok { "ok" } else { "degraded" },
+            "elasticsearch": elasticsearch,
+            "edges": edges,
+        })),
+        Err(e) => HttpResponse::ServiceUnavailable().json(json!({
+            "status": "error",
+            "elasticsearch": { "error": e },
+            "edges": edges,
+        })),
+    }
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/healthz").route(web::get().to(healthz)));
+    cfg.service(web::resource("/readyz").route(web::get().to(readyz)));
+}
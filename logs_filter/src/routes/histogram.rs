@@ -0,0 +1,125 @@
+use actix_web::web;
+use elasticsearch::SearchParts;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use crate::routes::search::SearchRequest;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+
+const DEFAULT_BUCKETS: u32 = 60;
+const MAX_BUCKETS: u32 = 500;
+const BREAKDOWN_SIZE: u32 = 10;
+
+// 可用于分组统计的维度 -> 聚合字段
+const BREAKDOWN_FIELDS: &[(&str, &str)] = &[
+    ("log_level", "log_level.keyword"),
+    ("service", "service.keyword"),
+    ("hostname", "hostname.keyword"),
+];
+
+#[derive(Deserialize)]
+pub struct HistogramRequest {
+    #[serde(flatten)]
+    search: SearchRequest, // 与 /search 相同的过滤条件
+    buckets: Option<u32>,  // 期望的时间桶数量，ES 自动选择间隔
+    breakdown: Option<Vec<String>>, // 分组维度，默认 log_level/service/hostname
+}
+
+pub async fn histogram(
+    request: web::Json<HistogramRequest>,
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<web::Json<Value>, ApiError> {
+    let filters = request.search.filters()?;
+
+    let breakdown: Vec<(&str, &str)> = match &request.breakdown {
+        None => BREAKDOWN_FIELDS.to_vec(),
+        Some(names) => {
+            let mut selected = Vec::new();
+            for name in names {
+                match BREAKDOWN_FIELDS.iter().find(|(n, _)| n == name) {
+                    Some(field) => selected.push(*field),
+                    None => return Err(ApiError::BadRequest(format!("Unsupported breakdown field: {}", name))),
+                }
+            }
+            selected
+        }
+    };
+
+    let mut sub_aggs = Map::new();
+    for (name, field) in &breakdown {
+        sub_aggs.insert(
+            name.to_string(),
+            json!({ "terms": { "field": field, "size": BREAKDOWN_SIZE } }),
+        );
+    }
+
+    let query = json!({
+        "size": 0,
+        "track_total_hits": true,
+        "query": {
+            "bool": {
+                "filter": filters
+            }
+        },
+        "aggs": {
+            "over_time": {
+                "auto_date_histogram": {
+                    "field": "@timestamp",
+                    "buckets": request.buckets.unwrap_or(DEFAULT_BUCKETS).clamp(1, MAX_BUCKETS),
+                    "format": "strict_date_optional_time"
+                },
+                "aggs": sub_aggs
+            }
+        }
+    });
+
+    let indices = request.search.indices(&routing);
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let response = es.send_with_retry(|| es
+        .search(SearchParts::Index(&indices))
+        .ignore_unavailable(true)
+        .body(query.clone())
+        .send())
+        .await;
+
+    let body = es_json(response).await?;
+    let over_time = &body["aggregations"]["over_time"];
+
+    // 每个时间桶：时间、总数以及各维度的 {值: 数量}
+    let buckets: Vec<Value> = over_time["buckets"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|bucket| {
+            let mut entry = json!({
+                "timestamp": bucket["key_as_string"],
+                "key": bucket["key"],
+                "count": bucket["doc_count"]
+            });
+            for (name, _) in &breakdown {
+                let counts: Map<String, Value> = bucket[*name]["buckets"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|b| Some((b["key"].as_str()?.to_string(), b["doc_count"].clone())))
+                    .collect();
+                entry[*name] = Value::Object(counts);
+            }
+            entry
+        })
+        .collect();
+
+    Ok(web::Json(json!({
+        "total": body["hits"]["total"]["value"],
+        "interval": over_time["interval"],
+        "buckets": buckets
+    })))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/histogram").route(web::post().to(histogram)));
+}
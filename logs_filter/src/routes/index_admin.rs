@@ -0,0 +1,256 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use elasticsearch::{
+    ilm::IlmPutLifecycleParts,
+    indices::{IndicesCloseParts, IndicesDeleteParts, IndicesPutSettingsParts},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::es_client::EsClient;
+use crate::config::{AdminConfig, Config};
+use crate::error::{es_json, ApiError};
+use crate::index_template::{bootstrap, put_template_lifecycle};
+use crate::routes::get_indices::list_indices;
+
+const DEFAULT_POLICY_NAME: &str = "jkzy-logs-retention";
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Deserialize)]
+pub struct IndexSelection {
+    #[serde(default)]
+    indices: Vec<String>,        // 指定的索引名（不支持通配符）
+    older_than_days: Option<u64>, // 或者选择创建时间早于 N 天的索引
+    #[serde(default)]
+    dry_run: bool,               // 只返回将被处理的索引
+}
+
+#[derive(Deserialize)]
+pub struct RetentionRequest {
+    policy_name: Option<String>,
+    delete_after_days: u64,        // 索引创建 N 天后删除
+    warm_after_days: Option<u64>,  // 索引创建 N 天后只读并 force merge
+    #[serde(default = "default_true")]
+    apply_to_existing: bool,       // 是否同时应用到现有索引
+}
+
+#[derive(Deserialize)]
+pub struct TemplateQuery {
+    #[serde(default)]
+    force: bool, // 无论已安装的版本如何都重新安装
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 常量时间比较，避免通过响应时间逐字节猜出令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// 校验请求头中的管理令牌
+pub(crate) fn authorize(req: &HttpRequest, admin: &AdminConfig) -> Result<(), ApiError> {
+    let Some(expected) = admin.token.as_deref().filter(|t| !t.is_empty()) else {
+        return Err(ApiError::Forbidden("Admin API is disabled".to_string()));
+    };
+    let provided = req
+        .headers()
+        .get("X-Admin-Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(ApiError::Forbidden("Invalid admin token".to_string()));
+    }
+    Ok(())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// 解析要处理的索引，只允许受管理前缀下的索引
+async fn resolve_selection(
+    es: &EsClient,
+    admin: &AdminConfig,
+    selection: &IndexSelection,
+    only_open: bool,
+) -> Result<Vec<String>, ApiError> {
+    if selection.indices.is_empty() && selection.older_than_days.is_none() {
+        return Err(ApiError::BadRequest("Either indices or older_than_days must be provided".to_string()));
+    }
+    // 0 天会选中包括当天写入索引在内的所有索引
+    if selection.older_than_days == Some(0) {
+        return Err(ApiError::BadRequest("older_than_days must be greater than 0".to_string()));
+    }
+    for name in &selection.indices {
+        if !name.starts_with(&admin.managed_index_prefix) || name.contains(['*', '?', ',']) {
+            return Err(ApiError::BadRequest(format!(
+                "Index {} is not managed by this tool (prefix {})",
+                name, admin.managed_index_prefix
+            )));
+        }
+    }
+
+    let pattern = format!("{}*", admin.managed_index_prefix);
+    let existing = list_indices(es, &pattern).await?;
+
+    let cutoff = selection
+        .older_than_days
+        .map(|days| now_millis().saturating_sub(days * DAY_MILLIS));
+
+    Ok(existing
+        .into_iter()
+        .filter(|info| !only_open || info.status == "open")
+        .filter(|info| {
+            selection.indices.contains(&info.index)
+                || cutoff.is_some_and(|cutoff| info.creation_date > 0 && info.creation_date < cutoff)
+        })
+        .map(|info| info.index)
+        .collect())
+}
+
+pub async fn delete_indices(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    selection: web::Json<IndexSelection>,
+) -> Result<HttpResponse, ApiError> {
+    let admin = &config.admin;
+    authorize(&req, admin)?;
+    let targets = resolve_selection(&es, admin, &selection, false).await?;
+    if selection.dry_run || targets.is_empty() {
+        return Ok(HttpResponse::Ok().json(json!({ "deleted": [], "matched": targets })));
+    }
+
+    let names: Vec<&str> = targets.iter().map(String::as_str).collect();
+    // ignore_unavailable 使重试时已删除的索引不报错
+    let indices = es.indices();
+    let response = es
+        .send_with_retry(|| indices.delete(IndicesDeleteParts::Index(&names)).ignore_unavailable(true).send())
+        .await;
+    es_json(response).await?;
+    Ok(HttpResponse::Ok().json(json!({ "deleted": targets })))
+}
+
+pub async fn close_indices(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    selection: web::Json<IndexSelection>,
+) -> Result<HttpResponse, ApiError> {
+    let admin = &config.admin;
+    authorize(&req, admin)?;
+    let targets = resolve_selection(&es, admin, &selection, true).await?;
+    if selection.dry_run || targets.is_empty() {
+        return Ok(HttpResponse::Ok().json(json!({ "closed": [], "matched": targets })));
+    }
+
+    let names: Vec<&str> = targets.iter().map(String::as_str).collect();
+    let indices = es.indices();
+    let response = es
+        .send_with_retry(|| indices.close(IndicesCloseParts::Index(&names)).ignore_unavailable(true).send())
+        .await;
+    es_json(response).await?;
+    Ok(HttpResponse::Ok().json(json!({ "closed": targets })))
+}
+
+// 创建/更新 ILM 保留策略，写入索引模板，并可应用到现有的受管理索引
+pub async fn put_retention(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    request: web::Json<RetentionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let admin = &config.admin;
+    authorize(&req, admin)?;
+    if request.delete_after_days == 0 {
+        return Err(ApiError::BadRequest("delete_after_days must be greater than 0".to_string()));
+    }
+    if request.warm_after_days.is_some_and(|warm| warm >= request.delete_after_days) {
+        return Err(ApiError::BadRequest("warm_after_days must be less than delete_after_days".to_string()));
+    }
+
+    let policy_name = request.policy_name.as_deref().unwrap_or(DEFAULT_POLICY_NAME);
+    let mut phases = json!({
+        "delete": {
+            "min_age": format!("{}d", request.delete_after_days),
+            "actions": { "delete": {} }
+        }
+    });
+    if let Some(warm) = request.warm_after_days {
+        phases["warm"] = json!({
+            "min_age": format!("{}d", warm),
+            "actions": {
+                "readonly": {},
+                "forcemerge": { "max_num_segments": 1 }
+            }
+        });
+    }
+
+    let ilm = es.ilm();
+    let put_policy = es.send_with_retry(|| ilm
+        .put_lifecycle(IlmPutLifecycleParts::Policy(policy_name))
+        .body(json!({ "policy": { "phases": phases } }))
+        .send())
+        .await;
+    es_json(put_policy).await?;
+
+    // 新建的索引通过模板获得策略
+    put_template_lifecycle(&es, &config.index_template, admin, &config.log_parsing, policy_name).await?;
+
+    let pattern = format!("{}*", admin.managed_index_prefix);
+    if request.apply_to_existing {
+        let indices = es.indices();
+        let patterns = [pattern.as_str()];
+        let apply = es.send_with_retry(|| indices
+            .put_settings(IndicesPutSettingsParts::Index(&patterns))
+            .body(json!({ "index.lifecycle.name": policy_name }))
+            .send())
+            .await;
+        es_json(apply).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "policy": policy_name,
+        "phases": phases,
+        "template": config.index_template.name,
+        "applied_to": if request.apply_to_existing { Some(pattern) } else { None }
+    })))
+}
+
+// 查看模板和 pipeline 的安装状态，以及现有索引的映射差异
+pub async fn template_status(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&req, &config.admin)?;
+    let report = bootstrap(&es, &config.index_template, &config.admin, &config.log_parsing, false, false).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// 安装/升级模板和 pipeline
+pub async fn install_template(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    query: web::Query<TemplateQuery>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&req, &config.admin)?;
+    let report = bootstrap(&es, &config.index_template, &config.admin, &config.log_parsing, true, query.force).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/index_admin/delete").route(web::post().to(delete_indices)));
+    cfg.service(web::resource("/index_admin/close").route(web::post().to(close_indices)));
+    cfg.service(web::resource("/index_admin/retention").route(web::put().to(put_retention)));
+    cfg.service(
+        web::resource("/index_admin/template")
+            .route(web::get().to(template_status))
+            .route(web::put().to(install_template)),
+    );
+}
@@ -1,20 +1,60 @@
-use actix_web::{web, Responder};
-use elasticsearch::{Elasticsearch, SearchParts};
+use actix_web::web;
+use elasticsearch::SearchParts;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use crate::highlight::HighlightOptions;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use crate::log_entry::{fields_param, map_hits};
 
 #[derive(Deserialize)]
 pub struct SearchRequest {
-    es_index: String, // ES index pattern (e.g., "rtc-logs-*")
+    #[serde(default)]
+    es_index: Option<String>, // ES index pattern (e.g., "jkzy-logs-*"), resolved from service and time range when omitted
     keyword: String,  // Search keyword for the multi_match query
+    #[serde(default)]
+    service: String,    // Optional service filter, also selects the service's indices
+    #[serde(default)]
+    start_time: String, // Optional time range
+    #[serde(default)]
+    end_time: String,
+    #[serde(default)]
+    fields: Vec<String>, // Extra fields to return, "*" for all fields
+    #[serde(default)]
+    highlight: HighlightOptions, // Highlight mode: tags / offsets / none
 }
 
 pub async fn keyword_search(
     request: web::Json<SearchRequest>,
-    es: web::Data<Elasticsearch>,
-) -> impl Responder {
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<web::Json<Value>, ApiError> {
+    let mut filters = vec![json!({
+        "match": {
+            "message": request.keyword,  // 使用请求中的关键字进行 multi_match 查询
+            // "type": "best_fields",
+            // "query": request.keyword, // Using request keyword for multi_match query
+            // "lenient": true
+        }
+    })];
+    if !request.service.is_empty() {
+        filters.push(json!({ "match_phrase": { "service": request.service } }));
+    }
+    if !request.start_time.is_empty() || !request.end_time.is_empty() {
+        filters.push(json!({
+            "range": {
+                "@timestamp": {
+                    "format": "strict_date_optional_time",
+                    "gte": Some(&request.start_time).filter(|t| !t.is_empty()),
+                    "lte": Some(&request.end_time).filter(|t| !t.is_empty())
+                }
+            }
+        }));
+    }
+
     // Construct the query body
-    let query = json!({
+    let mut query = json!({
         "track_total_hits": false,
         "sort": [
             {
@@ -24,16 +64,7 @@ pub async fn keyword_search(
                 }
             }
         ],
-        "fields": [
-            {
-                "field": "*",
-                "include_unmapped": true
-            },
-            {
-                "field": "@timestamp",
-                "format": "strict_date_optional_time"
-            }
-        ],
+        "fields": fields_param(&request.fields),
         "size": 500,
         "version": true,
         "script_fields": {},
@@ -45,105 +76,37 @@ pub async fn keyword_search(
         "query": {
             "bool": {
                 "must": [],
-                "filter": [
-                    {
-                        "match": {
-                            "message": request.keyword,  // 使用请求中的关键字进行 multi_match 查询
-                            // "type": "best_fields",
-                            // "query": request.keyword, // Using request keyword for multi_match query
-                            // "lenient": true
-                        }
-                    }
-                ],
+                "filter": filters,
                 "should": [],
                 "must_not": []
             }
-        },
-        "highlight": {
-            "pre_tags": [
-                "@kibana-highlighted-field@"
-            ],
-            "post_tags": [
-                "@/kibana-highlighted-field@"
-            ],
-            "fields": {
-                "*": {}
-            },
-            "fragment_size": 2147483647
         }
     });
+    if let Some(highlight) = request.highlight.query_param() {
+        query["highlight"] = highlight;
+    }
 
     // Execute the query
-    let response = es
-        .search(SearchParts::Index(&[&request.es_index])) // Use the index pattern from the request
-        .body(query)
-        .send()
+    let indices = match request.es_index.as_deref().filter(|index| !index.is_empty()) {
+        Some(index) => vec![index.to_string()],
+        None => routing.resolve_request(&request.service, &request.start_time, &request.end_time),
+    };
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let response = es.send_with_retry(|| es
+        .search(SearchParts::Index(&indices)) // Use the index pattern from the request or the resolved indices
+        .ignore_unavailable(true)
+        .body(query.clone())
+        .send())
         .await;
 
-    // Handle the response
-    match response {
-        Ok(response) => {
-            // Assuming the `response` is the JSON response you received
-            let body = response.json::<Value>().await.unwrap();
-
-            // Initialize default value for hits
-            let default_hits = vec![];
-
-            // Extract relevant data from the body
-            let hits = body["hits"]["hits"].as_array().unwrap_or(&default_hits);
-
-            // Prepare the result for JSON response
-            let mut result = Vec::new();
+    // Handle the response, ES errors are returned with their root cause
+    let body = es_json(response).await?;
 
-            for hit in hits {
-                let index = hit["_index"].as_str().unwrap_or_default();
-                let file_name = hit["fields"]["log.file.path"]
-                    .as_array()
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default();
-                let message = hit["fields"]["event.original"]
-                    .as_array()
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default();
-                let timestamp = hit["fields"]["@timestamp"]
-                    .as_array()
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default();
-                let service = hit["fields"]["service"]
-                    .as_array()
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default();
-                let hostname = hit["fields"]["hostname"]
-                    .as_array()
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default();
+    // Map hits to the shared LogEntry schema
+    let result = map_hits(&body, &request.fields, &request.highlight);
 
-                let entry = json!({
-                "_index": index,
-                "file_name": file_name,
-                "message": message,
-                "timestamp": timestamp,
-                "service": service,
-                "hostname": hostname
-                });
-                result.push(entry);
-            }
-            // Return the results in JSON format
-            web::Json(json!({ "results": result }))
-        }
-        Err(e) => {
-            let error_message = format!("Error during search: {}", e);
-            web::Json(json!({
-                "results": [],
-                "error": error_message
-            }))
-        }
-    }
+    // Return the results in JSON format
+    Ok(web::Json(json!({ "results": result })))
 }
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
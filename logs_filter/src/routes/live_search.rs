@@ -0,0 +1,295 @@
+use actix_web::{http::header, web, HttpResponse};
+use chrono::DateTime;
+use elasticsearch::SearchParts;
+use futures::stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::time::Duration;
+use crate::error::{es_json, ApiError};
+use crate::highlight::{HighlightMode, HighlightOptions};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use crate::log_entry::{fields_param, map_hits};
+use crate::routes::search::SearchRequest;
+use crate::saved_search_store::now_millis;
+
+// 实时搜索（live tail）：通过 SSE 推送新写入 ES 且匹配查询的日志。
+// 按 @timestamp 升序，以主机、文件路径和文件偏移作为同一毫秒内的次序轮询：
+// 一批取满时用最后一条的完整 sort 值作为 search_after 继续翻页，同一毫秒内的文档再多也能向前推进；
+// 追上最新数据后从已推送的最大时间戳（含）重新查询，同一毫秒内的文档用 _id 去重，
+// 因此同一毫秒内分批写入的文档不会重复或遗漏。写入延迟导致时间戳早于已推送最大时间戳的文档不会再推送。
+
+const BATCH_SIZE: usize = 500;
+const DEFAULT_INTERVAL_MS: u64 = 2000;
+const MIN_INTERVAL_MS: u64 = 500;
+const MAX_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Deserialize)]
+pub struct LiveSearchParams {
+    es_index: Option<String>, // 省略时按 service 和当前时间由 index_routing 解析，跨天时自动切换到新索引
+    #[serde(default)]
+    keyword: String,
+    #[serde(default)]
+    hostname: String,
+    #[serde(default)]
+    service: String,
+    #[serde(default)]
+    basename: String,
+    #[serde(default)]
+    query: String,          // 查询表达式，与 /search 相同
+    since: Option<u64>,     // 从该时间（epoch 毫秒）开始推送，默认为连接时刻
+    interval_ms: Option<u64>, // 轮询间隔
+}
+
+// 同一毫秒内的次序，缺失的字段排在最后
+fn sort_clause() -> Value {
+    json!([
+        { "@timestamp": { "order": "asc", "format": "epoch_millis" } },
+        { "hostname.keyword": { "order": "asc", "unmapped_type": "keyword" } },
+        { "log.file.path.keyword": { "order": "asc", "unmapped_type": "keyword" } },
+        { "log.offset": { "order": "asc", "unmapped_type": "long" } }
+    ])
+}
+
+// 轮询位置
+#[derive(Default)]
+struct TailCursor {
+    last_timestamp: u64,           // 已推送文档的最大时间戳（epoch 毫秒）
+    seen_at_last: HashSet<String>, // 时间戳等于 last_timestamp 的已推送文档 _id
+    search_after: Option<Value>,   // 上一批取满时最后一条的 sort 值
+}
+
+impl TailCursor {
+    fn new(since: u64) -> TailCursor {
+        TailCursor { last_timestamp: since, ..Default::default() }
+    }
+
+    // filters 的第一个条件是时间范围；追上最新数据后改为从 last_timestamp（含）开始
+    fn query(&self, filters: &[Value]) -> Value {
+        let mut filters = filters.to_vec();
+        let mut query = json!({
+            "size": BATCH_SIZE,
+            "_source": false,
+            "fields": fields_param(&[]),
+            "track_total_hits": false,
+            "sort": sort_clause(),
+        });
+        match &self.search_after {
+            Some(sort) => query["search_after"] = sort.clone(),
+            None => filters[0] = json!({ "range": { "@timestamp": { "gte": self.last_timestamp, "format": "epoch_millis" } } }),
+        }
+        query["query"] = json!({ "bool": { "filter": filters } });
+        query
+    }
+
+    // 返回需要推送的命中下标并更新位置，批次取满时返回 true
+    fn advance(&mut self, hits: &[Value]) -> (Vec<usize>, bool) {
+        let mut fresh = Vec::new();
+        for (i, hit) in hits.iter().enumerate() {
+            let timestamp = hit["sort"][0]
+                .as_u64()
+                .or_else(|| hit["sort"][0].as_str().and_then(|s| s.parse().ok()))
+                .unwrap_or(self.last_timestamp);
+            if timestamp > self.last_timestamp {
+                self.last_timestamp = timestamp;
+                self.seen_at_last.clear();
+            }
+            let id = hit["_id"].as_str().unwrap_or_default().to_string();
+            if timestamp < self.last_timestamp || !self.seen_at_last.insert(id) {
+                continue;
+            }
+            fresh.push(i);
+        }
+        let full = hits.len() == BATCH_SIZE;
+        self.search_after = if full { hits.last().map(|hit| hit["sort"].clone()) } else { None };
+        (fresh, full)
+    }
+}
+
+struct LiveState {
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+    es_index: Option<String>,
+    service: String,
+    filters: Vec<Value>,
+    cursor: TailCursor,
+    interval: Duration,
+    first: bool,
+    backlog: bool, // 上一批已满，立即继续拉取
+}
+
+fn sse_event(event: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+impl LiveState {
+    fn indices(&self) -> Vec<String> {
+        match self.es_index.as_deref().filter(|index| !index.is_empty()) {
+            Some(index) => vec![index.to_string()],
+            None => {
+                let since = DateTime::from_timestamp_millis(self.cursor.last_timestamp as i64);
+                self.routing.resolve(Some(&self.service), since, None)
+            }
+        }
+    }
+
+    async fn poll(&mut self) -> Result<String, ApiError> {
+        let query = self.cursor.query(&self.filters);
+
+        let indices = self.indices();
+        let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+        let response = self.es.send_with_retry(|| self.es
+            .search(SearchParts::Index(&indices))
+            .ignore_unavailable(true)
+            .body(query.clone())
+            .send())
+            .await;
+        let body = es_json(response).await?;
+
+        let hits = body["hits"]["hits"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        let no_highlight = HighlightOptions { mode: HighlightMode::None, ..Default::default() };
+        let entries = map_hits(&body, &[], &no_highlight);
+        let (fresh, full) = self.cursor.advance(hits);
+        let mut out = String::new();
+        for i in fresh {
+            out.push_str(&sse_event("log", &json!(entries[i])));
+        }
+        // 批次取满时立即从最后一条继续翻页（即使整批都已推送过）
+        self.backlog = full;
+        Ok(out)
+    }
+}
+
+async fn next_event(mut state: LiveState) -> Option<(Result<web::Bytes, actix_web::Error>, LiveState)> {
+    if !state.first && !state.backlog {
+        tokio::time::sleep(state.interval).await;
+    }
+    state.first = false;
+
+    let chunk = match state.poll().await {
+        // 没有新数据时发送注释行作为心跳，客户端断开后写入失败即停止轮询
+        Ok(events) if events.is_empty() => ": keep-alive\n\n".to_string(),
+        Ok(events) => events,
+        Err(e) => {
+            state.backlog = false;
+            sse_event("error", &e.body())
+        }
+    };
+    Some((Ok(web::Bytes::from(chunk)), state))
+}
+
+pub async fn live_search(
+    params: web::Query<LiveSearchParams>,
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<HttpResponse, ApiError> {
+    let params = params.into_inner();
+    let since = params.since.unwrap_or_else(now_millis);
+
+    // 复用 /search 的过滤条件，时间范围从 since 开始
+    let request: Result<SearchRequest, _> = serde_json::from_value(json!({
+        "es_index": params.es_index,
+        "keyword": params.keyword,
+        "hostname": params.hostname,
+        "service": params.service,
+        "basename": params.basename,
+        "query": params.query,
+        "start_time": "",
+        "end_time": ""
+    }));
+    let mut filters = request
+        .map_err(|e| ApiError::BadRequest(format!("Invalid query: {}", e)))?
+        .filters()?;
+    // 第一个条件是时间范围，改为从 since（epoch 毫秒）开始且不设上限
+    filters[0] = json!({ "range": { "@timestamp": { "gte": since, "format": "epoch_millis" } } });
+
+    let state = LiveState {
+        es,
+        routing,
+        es_index: params.es_index,
+        service: params.service,
+        filters,
+        cursor: TailCursor::new(since),
+        interval: Duration::from_millis(
+            params.interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+        ),
+        first: true,
+        backlog: false,
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream::unfold(state, next_event)))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/live_search").route(web::get().to(live_search)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    // 按 sort 值比较，依次比较时间戳、主机、路径、偏移
+    fn compare(a: &Value, b: &Value) -> Ordering {
+        let key = |v: &Value| {
+            (
+                v[0].as_str().and_then(|s| s.parse::<u64>().ok()).unwrap_or_default(),
+                v[1].as_str().unwrap_or_default().to_string(),
+                v[2].as_str().unwrap_or_default().to_string(),
+                v[3].as_u64().unwrap_or_default(),
+            )
+        };
+        key(a).cmp(&key(b))
+    }
+
+    // 模拟 ES：按 sort 值排序，应用时间范围和 search_after
+    fn search(docs: &[Value], query: &Value) -> Vec<Value> {
+        let gte = query["query"]["bool"]["filter"][0]["range"]["@timestamp"]["gte"].as_u64().unwrap_or_default();
+        let mut hits: Vec<Value> = docs
+            .iter()
+            .filter(|doc| doc["sort"][0].as_str().unwrap().parse::<u64>().unwrap() >= gte)
+            .filter(|doc| query.get("search_after").is_none_or(|after| compare(&doc["sort"], after) == Ordering::Greater))
+            .cloned()
+            .collect();
+        hits.sort_by(|a, b| compare(&a["sort"], &b["sort"]));
+        hits.truncate(query["size"].as_u64().unwrap() as usize);
+        hits
+    }
+
+    fn doc(id: usize, timestamp: u64) -> Value {
+        json!({ "_id": format!("doc-{}", id), "sort": [timestamp.to_string(), "web-1", "/var/log/app.log", id] })
+    }
+
+    // 同一毫秒内的文档超过一批时不会卡住，也不会重复推送
+    #[test]
+    fn pages_through_equal_timestamps() {
+        let since = 1_000;
+        let mut docs: Vec<Value> = (0..BATCH_SIZE * 2 + 37).map(|i| doc(i, since + 5)).collect();
+        docs.push(doc(10_000, since + 6));
+        let filters = vec![json!({ "range": { "@timestamp": { "gte": since } } })];
+
+        let mut cursor = TailCursor::new(since);
+        let mut pushed = Vec::new();
+        for _ in 0..10 {
+            let hits = search(&docs, &cursor.query(&filters));
+            let (fresh, _) = cursor.advance(&hits);
+            pushed.extend(fresh.into_iter().map(|i| hits[i]["_id"].as_str().unwrap().to_string()));
+        }
+        assert_eq!(pushed.len(), docs.len());
+        assert_eq!(pushed.iter().collect::<HashSet<_>>().len(), docs.len());
+        assert_eq!(cursor.last_timestamp, since + 6);
+
+        // 追上后同一毫秒内新写入的文档仍会推送
+        docs.push(doc(10_001, since + 6));
+        let hits = search(&docs, &cursor.query(&filters));
+        let (fresh, full) = cursor.advance(&hits);
+        assert_eq!(fresh.iter().map(|&i| hits[i]["_id"].as_str().unwrap()).collect::<Vec<_>>(), vec!["doc-10001"]);
+        assert!(!full);
+    }
+}
@@ -0,0 +1,15 @@
+use actix_web::{web, HttpResponse};
+use prometheus::TEXT_FORMAT;
+use serde_json::json;
+use crate::telemetry;
+
+pub async fn metrics() -> HttpResponse {
+    match telemetry::render() {
+        Ok(body) => HttpResponse::Ok().content_type(TEXT_FORMAT).body(body),
+        Err(e) => HttpResponse::InternalServerError().json(json!({ "error": format!("Failed to encode metrics: {}", e) })),
+    }
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/metrics").route(web::get().to(metrics)));
+}
@@ -1,6 +1,16 @@
 
-pub mod unique_services;
+pub mod field_values;
 pub mod search;
 pub mod get_indices;
 pub mod discover_node;
-pub mod keyword_search;
\ No newline at end of file
+pub mod keyword_search;
+pub mod context;
+pub mod histogram;
+pub mod index_admin;
+pub mod export;
+pub mod saved_searches;
+pub mod alerts;
+pub mod live_search;
+pub mod health;
+pub mod metrics;
+pub mod parsing;
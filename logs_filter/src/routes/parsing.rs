@@ -0,0 +1,97 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use elasticsearch::ingest::{IngestPutPipelineParts, IngestSimulateParts};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::es_client::EsClient;
+use crate::config::Config;
+use crate::error::{es_json, ApiError};
+use crate::log_parsing::{build_pipeline, LogParser};
+use crate::routes::index_admin::authorize;
+
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    service: String,
+    lines: Vec<String>,
+    #[serde(default)]
+    simulate: bool, // 同时用 ES 的 _simulate 执行生成的 pipeline，检查 ES 端的正则是否一致
+}
+
+// 查看根据配置生成的 ingest pipeline
+pub async fn get_pipeline(config: web::Data<Config>) -> Result<HttpResponse, ApiError> {
+    let config = &config.log_parsing;
+    let rules = config.effective_rules();
+    LogParser::new(&rules).map_err(ApiError::BadRequest)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "pipeline_id": config.pipeline_id,
+        "rules": rules,
+        "pipeline": build_pipeline(&rules),
+    })))
+}
+
+// 用配置的规则解析样例行
+pub async fn preview(
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+    request: web::Json<PreviewRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let rules = config.log_parsing.effective_rules();
+    let parser = LogParser::new(&rules).map_err(ApiError::BadRequest)?;
+    let results: Vec<Value> = request
+        .lines
+        .iter()
+        .map(|line| json!({ "line": line, "parsed": parser.parse(&request.service, line) }))
+        .collect();
+
+    let mut response = json!({ "service": request.service, "results": results });
+    if request.simulate {
+        let docs: Vec<Value> = request
+            .lines
+            .iter()
+            .map(|line| json!({ "_source": { "message": line, "service": request.service } }))
+            .collect();
+        let body = json!({ "pipeline": build_pipeline(&rules), "docs": docs });
+        let ingest = es.ingest();
+        let simulate = es.send_with_retry(|| ingest.simulate(IngestSimulateParts::None).body(body.clone()).send()).await;
+        let simulated = es_json(simulate).await?;
+        response["simulated"] = simulated["docs"].clone();
+    }
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// 校验规则和样例行后安装（或覆盖）pipeline
+pub async fn install_pipeline(
+    req: HttpRequest,
+    es: web::Data<EsClient>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    authorize(&req, &config.admin)?;
+    let config = &config.log_parsing;
+    let rules = config.effective_rules();
+    let parser = LogParser::new(&rules).map_err(ApiError::BadRequest)?;
+    let failures = parser.check_samples();
+    if !failures.is_empty() {
+        return Err(ApiError::BadRequest(format!("Sample lines do not parse: {}", failures.join("; "))));
+    }
+
+    let ingest = es.ingest();
+    let pipeline = build_pipeline(&rules);
+    let response = es.send_with_retry(|| ingest
+        .put_pipeline(IngestPutPipelineParts::Id(&config.pipeline_id))
+        .body(pipeline.clone())
+        .send())
+        .await;
+    let body = es_json(response).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "pipeline_id": config.pipeline_id,
+        "acknowledged": body["acknowledged"],
+    })))
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/log_parsing/pipeline")
+            .route(web::get().to(get_pipeline))
+            .route(web::put().to(install_pipeline)),
+    );
+    cfg.service(web::resource("/log_parsing/preview").route(web::post().to(preview)));
+}
@@ -0,0 +1,130 @@
+use actix_web::{http::header, web, HttpResponse, Responder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::es_client::EsClient;
+use crate::error::ApiError;
+use crate::index_routing::IndexRouting;
+use crate::routes::search::{search_logs, SearchRequest};
+use crate::saved_search_store::{SavedSearch, SavedSearchStore};
+
+#[derive(Deserialize)]
+pub struct SavedSearchBody {
+    name: String,
+    #[serde(default)]
+    description: String,
+    request: Value, // /search 的请求体
+}
+
+// 分享链接：打开前端页面并加载保存的搜索
+fn share_link(id: &str) -> String {
+    format!("/s/{}", id)
+}
+
+fn with_link(saved: &SavedSearch) -> Value {
+    let mut value = json!(saved);
+    value["link"] = Value::String(share_link(&saved.id));
+    value
+}
+
+// 保存前校验请求体能被 /search 接受
+fn validate(body: &SavedSearchBody) -> Result<(), ApiError> {
+    if body.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("name must not be empty".to_string()));
+    }
+    let request: SearchRequest = serde_json::from_value(body.request.clone())
+        .map_err(|e| ApiError::BadRequest(format!("Invalid search request: {}", e)))?;
+    request.filters()?;
+    Ok(())
+}
+
+fn not_found(id: &str) -> ApiError {
+    ApiError::NotFound(format!("Saved search {} not found", id))
+}
+
+pub async fn list_saved_searches(store: web::Data<SavedSearchStore>) -> impl Responder {
+    let searches: Vec<Value> = store.list().iter().map(with_link).collect();
+    web::Json(json!({ "saved_searches": searches }))
+}
+
+pub async fn create_saved_search(
+    store: web::Data<SavedSearchStore>,
+    body: web::Json<SavedSearchBody>,
+) -> Result<HttpResponse, ApiError> {
+    validate(&body)?;
+    let body = body.into_inner();
+    let saved = store
+        .create(body.name, body.description, body.request)
+        .map_err(|e| ApiError::Internal(format!("Failed to save search: {}", e)))?;
+    Ok(HttpResponse::Created().json(with_link(&saved)))
+}
+
+pub async fn get_saved_search(
+    store: web::Data<SavedSearchStore>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let saved = store.get(&id).ok_or_else(|| not_found(&id))?;
+    Ok(HttpResponse::Ok().json(with_link(&saved)))
+}
+
+pub async fn update_saved_search(
+    store: web::Data<SavedSearchStore>,
+    id: web::Path<String>,
+    body: web::Json<SavedSearchBody>,
+) -> Result<HttpResponse, ApiError> {
+    validate(&body)?;
+    let body = body.into_inner();
+    let saved = store
+        .update(&id, body.name, body.description, body.request)
+        .map_err(|e| ApiError::Internal(format!("Failed to save search: {}", e)))?
+        .ok_or_else(|| not_found(&id))?;
+    Ok(HttpResponse::Ok().json(with_link(&saved)))
+}
+
+pub async fn delete_saved_search(
+    store: web::Data<SavedSearchStore>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let deleted = store
+        .delete(&id)
+        .map_err(|e| ApiError::Internal(format!("Failed to delete search: {}", e)))?;
+    if !deleted {
+        return Err(not_found(&id));
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// 以保存的请求体执行 /search
+pub async fn run_saved_search(
+    store: web::Data<SavedSearchStore>,
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+    id: web::Path<String>,
+) -> Result<web::Json<Value>, ApiError> {
+    let saved = store.get(&id).ok_or_else(|| not_found(&id))?;
+    let request = serde_json::from_value::<SearchRequest>(saved.request)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid search request: {}", e)))?;
+    search_logs(web::Json(request), es, routing).await
+}
+
+// 分享链接跳转到前端页面
+pub async fn open_shared_link(id: web::Path<String>) -> HttpResponse {
+    HttpResponse::Found()
+        .insert_header((header::LOCATION, format!("/admin/?saved={}", id)))
+        .finish()
+}
+
+pub fn init_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/saved_searches")
+            .route(web::get().to(list_saved_searches))
+            .route(web::post().to(create_saved_search)),
+    );
+    cfg.service(
+        web::resource("/saved_searches/{id}")
+            .route(web::get().to(get_saved_search))
+            .route(web::put().to(update_saved_search))
+            .route(web::delete().to(delete_saved_search)),
+    );
+    cfg.service(web::resource("/saved_searches/{id}/run").route(web::post().to(run_saved_search)));
+    cfg.service(web::resource("/s/{id}").route(web::get().to(open_shared_link)));
+}
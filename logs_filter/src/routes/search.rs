@@ -1,26 +1,90 @@
-use actix_web::{web, Responder};
-use elasticsearch::{Elasticsearch, SearchParts};
+use actix_web::web;
+use elasticsearch::SearchParts;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use crate::highlight::HighlightOptions;
+use crate::error::{es_json, ApiError};
+use crate::es_client::EsClient;
+use crate::index_routing::IndexRouting;
+use crate::log_entry::{fields_param, map_hits};
+use crate::query_parser::{compile_query, ParseError};
 
 #[derive(Deserialize)]
 pub struct SearchRequest {
-    es_index: String,
+    #[serde(default)]
+    es_index: Option<String>, // 指定索引模式；省略时根据 service 和时间范围由 index_routing 解析
+    #[serde(default)]
     keyword: String,   // 用于 multi_match 查询的关键字
     start_time: String, // 时间范围的开始时间
     end_time: String,   // 时间范围的结束时间
+    #[serde(default)]
     hostname: String,   // 主机名
+    #[serde(default)]
     service: String,    // 服务名
+    #[serde(default)]
     basename: String,   // 文件名
+    #[serde(default)]
+    query: String,      // 查询表达式，如 service:RTC AND level:ERROR NOT hostname:test-*
+    #[serde(default)]
+    fields: Vec<String>, // 额外返回的字段，"*" 表示全部字段
+    #[serde(default)]
+    highlight: HighlightOptions, // 高亮方式：tags / offsets / none
 }
 
 
+impl SearchRequest {
+    pub(crate) fn indices(&self, routing: &IndexRouting) -> Vec<String> {
+        match self.es_index.as_deref().filter(|index| !index.is_empty()) {
+            Some(index) => vec![index.to_string()],
+            None => routing.resolve_request(&self.service, &self.start_time, &self.end_time),
+        }
+    }
+
+    pub(crate) fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    // 根据请求构造 bool 查询的 filter 条件，其他搜索类接口（如 /histogram）共用；
+    // 第一个条件总是时间范围
+    pub(crate) fn filters(&self) -> Result<Vec<Value>, ParseError> {
+        let mut filters = vec![json!({
+            "range": {
+                "@timestamp": {
+                    "format": "strict_date_optional_time",
+                    "gte": self.start_time,
+                    "lte": self.end_time
+                }
+            }
+        })];
+
+        // 表单字段为空时不参与过滤
+        for (field, value) in [
+            ("message", &self.keyword),
+            ("hostname", &self.hostname),
+            ("service", &self.service),
+            ("basename", &self.basename),
+        ] {
+            if !value.is_empty() {
+                filters.push(json!({ "match_phrase": { field: value } }));
+            }
+        }
+
+        if !self.query.trim().is_empty() {
+            filters.push(compile_query(&self.query)?);
+        }
+        Ok(filters)
+    }
+}
+
 pub async fn search_logs(
     request: web::Json<SearchRequest>,
-    es: web::Data<Elasticsearch>,
-) -> impl Responder {
+    es: web::Data<EsClient>,
+    routing: web::Data<IndexRouting>,
+) -> Result<web::Json<Value>, ApiError> {
+    let filters = request.filters()?;
+
     // 构造查询体
-    let query = json!({
+    let mut query = json!({
         "track_total_hits": false,
         "sort": [
             {
@@ -30,136 +94,35 @@ pub async fn search_logs(
                 }
             }
         ],
-        "fields": [
-            {
-                "field": "*",
-                "include_unmapped": true
-            },
-            {
-                "field": "@timestamp",
-                "format": "strict_date_optional_time"
-            }
-        ],
+        "fields": fields_param(&request.fields),
+        "_source": false,
         "size": 500,
         "query": {
             "bool": {
-                "filter": [
-                    {
-                        "match_phrase": {
-                            "message": request.keyword,  // 使用请求中的关键字进行 multi_match 查询
-                        }
-                    },
-                    {
-                        "range": {
-                            "@timestamp": {
-                                "format": "strict_date_optional_time",
-                                "gte": request.start_time,
-                                "lte": request.end_time
-                            }
-                        }
-                    },
-                    {
-                        "bool": {
-                            "must": [],
-                            "filter": [
-                                {
-                                    "match_phrase": {
-                                        "hostname": request.hostname
-                                    }
-                                },
-                                {
-                                    "match_phrase": {
-                                        "service": request.service
-                                    }
-                                },
-                                {
-                                    "match_phrase": {
-                                        "basename": request.basename
-                                    }
-                                }
-                            ],
-                            "should": [],
-                            "must_not": []
-                        }
-                    }
-                ]
+                "filter": filters
             }
-        },
-        "highlight": {
-            "pre_tags": ["@kibana-highlighted-field@"],
-            "post_tags": ["@/kibana-highlighted-field@"],
-            "fields": {
-                "*": {}
-            },
-            "fragment_size": 2147483647
         }
     });
+    if let Some(highlight) = request.highlight.query_param() {
+        query["highlight"] = highlight;
+    }
 
     // 执行查询
-    let response = es
-        .search(SearchParts::Index(&[&request.es_index])) // 替换为目标索引
-        .body(query)
-        .send()
+    let indices = request.indices(&routing);
+    let indices: Vec<&str> = indices.iter().map(String::as_str).collect();
+    let response = es.send_with_retry(|| es
+        .search(SearchParts::Index(&indices))
+        .ignore_unavailable(true) // 按天解析的索引可能不存在
+        .body(query.clone())
+        .send())
         .await;
 
     // 处理响应
-    match response {
-        Ok(response) => {
-            let body = response.json::<Value>().await.unwrap();
-            let default_hits = vec![]; // 默认值
-            let hits = body["hits"]["hits"].as_array().unwrap_or(&default_hits);
-
-            let results: Vec<Value> = hits
-                .iter()
-                .map(|hit| {
-                    let source = &hit["_source"];
-                    let hostname = source
-                        .get("hostname")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_owned();
-                    let basename = source
-                        .get("basename")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_owned();
-                    let message = source
-                        .get("message")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_owned();
-                    let timestamp = source
-                        .get("@timestamp")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_owned();
-                    let log_level = source
-                        .get("log_level")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_owned();
-
-                    json!({
-                        "hostname": hostname,
-                        "basename": basename,
-                        "message": message,
-                        "timestamp": timestamp,
-                        "log_level": log_level
-                    })
-                })
-                .collect();
+    let body = es_json(response).await?;
+    let results = map_hits(&body, &request.fields, &request.highlight);
 
-            // 返回 JSON 格式的结果
-            web::Json(json!({ "results": results }))
-        }
-        Err(e) => {
-            let error_message = format!("Error during search: {}", e);
-            web::Json(json!({
-                "results": [],
-                "error": error_message
-            }))
-        }
-    }
+    // 返回 JSON 格式的结果
+    Ok(web::Json(json!({ "results": results })))
 }
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 保存的搜索：以 JSON 文件持久化，每次修改后整体写回
+
+const ID_LENGTH: usize = 8;
+const ID_ALPHABET: &[u8] = b"0123456789abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub request: Value, // 与 /search 请求体完全相同
+    pub created_at: u64, // epoch 毫秒
+    pub updated_at: u64,
+}
+
+pub struct SavedSearchStore {
+    path: PathBuf,
+    searches: RwLock<HashMap<String, SavedSearch>>,
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// 生成短 id，RandomState 每次使用随机种子
+fn generate_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default());
+    let mut n = hasher.finish();
+    (0..ID_LENGTH)
+        .map(|_| {
+            let c = ID_ALPHABET[(n % ID_ALPHABET.len() as u64) as usize] as char;
+            n /= ID_ALPHABET.len() as u64;
+            c
+        })
+        .collect()
+}
+
+impl SavedSearchStore {
+    // 从文件加载，文件不存在时从空开始
+    pub fn open(path: &str) -> Result<SavedSearchStore, Box<dyn std::error::Error>> {
+        let path = PathBuf::from(path);
+        let searches = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str::<Vec<SavedSearch>>(&contents)?
+                .into_iter()
+                .map(|s| (s.id.clone(), s))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(SavedSearchStore {
+            path,
+            searches: RwLock::new(searches),
+        })
+    }
+
+    // 先写临时文件再重命名，避免写到一半时崩溃损坏数据
+    fn persist(&self, searches: &HashMap<String, SavedSearch>) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut list: Vec<&SavedSearch> = searches.values().collect();
+        list.sort_by_key(|s| s.created_at);
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&list)?)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn list(&self) -> Vec<SavedSearch> {
+        let searches = self.searches.read().unwrap_or_else(|e| e.into_inner());
+        let mut list: Vec<SavedSearch> = searches.values().cloned().collect();
+        list.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        list
+    }
+
+    pub fn get(&self, id: &str) -> Option<SavedSearch> {
+        let searches = self.searches.read().unwrap_or_else(|e| e.into_inner());
+        searches.get(id).cloned()
+    }
+
+    pub fn create(&self, name: String, description: String, request: Value) -> std::io::Result<SavedSearch> {
+        let mut searches = self.searches.write().unwrap_or_else(|e| e.into_inner());
+        let mut id = generate_id();
+        while searches.contains_key(&id) {
+            id = generate_id();
+        }
+        let now = now_millis();
+        let saved = SavedSearch {
+            id: id.clone(),
+            name,
+            description,
+            request,
+            created_at: now,
+            updated_at: now,
+        };
+        searches.insert(id.clone(), saved.clone());
+        if let Err(e) = self.persist(&searches) {
+            searches.remove(&id);
+            return Err(e);
+        }
+        Ok(saved)
+    }
+
+    pub fn update(&self, id: &str, name: String, description: String, request: Value) -> std::io::Result<Option<SavedSearch>> {
+        let mut searches = self.searches.write().unwrap_or_else(|e| e.into_inner());
+        let Some(previous) = searches.get(id).cloned() else {
+            return Ok(None);
+        };
+        let saved = SavedSearch {
+            name,
+            description,
+            request,
+            updated_at: now_millis(),
+            ..previous.clone()
+        };
+        searches.insert(id.to_string(), saved.clone());
+        if let Err(e) = self.persist(&searches) {
+            searches.insert(id.to_string(), previous);
+            return Err(e);
+        }
+        Ok(Some(saved))
+    }
+
+    pub fn delete(&self, id: &str) -> std::io::Result<bool> {
+        let mut searches = self.searches.write().unwrap_or_else(|e| e.into_inner());
+        let Some(previous) = searches.remove(id) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.persist(&searches) {
+            searches.insert(id.to_string(), previous);
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,105 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Instant;
+
+// Prometheus 指标：HTTP 请求数和耗时、ES 查询耗时和错误、边缘节点发现失败次数
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "logs_filter_http_requests_total",
+        "HTTP requests by route, method and status",
+        &["route", "method", "status"]
+    )
+    .expect("register logs_filter_http_requests_total")
+});
+
+static HTTP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "logs_filter_http_request_duration_seconds",
+        "HTTP request latency by route and method",
+        &["route", "method"]
+    )
+    .expect("register logs_filter_http_request_duration_seconds")
+});
+
+static ES_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "logs_filter_es_request_duration_seconds",
+        "Elasticsearch request latency by outcome",
+        &["outcome"]
+    )
+    .expect("register logs_filter_es_request_duration_seconds")
+});
+
+static ES_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "logs_filter_es_errors_total",
+        "Elasticsearch errors by kind (transport, 4xx, 5xx)",
+        &["kind"]
+    )
+    .expect("register logs_filter_es_errors_total")
+});
+
+static EDGE_DISCOVERY_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "logs_filter_edge_discovery_failures_total",
+        "Failed log source discovery requests by edge and reason",
+        &["edge", "reason"]
+    )
+    .expect("register logs_filter_edge_discovery_failures_total")
+});
+
+// 记录一次 ES 请求；status 为 None 表示连接失败或超时
+pub fn observe_es_request(started: Instant, status: Option<u16>) {
+    let (outcome, error_kind) = match status {
+        None => ("error", Some("transport")),
+        Some(code) if code >= 500 => ("error", Some("5xx")),
+        Some(code) if code >= 400 => ("error", Some("4xx")),
+        Some(_) => ("success", None),
+    };
+    ES_DURATION
+        .with_label_values(&[outcome])
+        .observe(started.elapsed().as_secs_f64());
+    if let Some(kind) = error_kind {
+        ES_ERRORS.with_label_values(&[kind]).inc();
+    }
+}
+
+pub fn edge_discovery_failed(edge: &str, reason: &str) {
+    EDGE_DISCOVERY_FAILURES.with_label_values(&[edge, reason]).inc();
+}
+
+// 请求计数与耗时中间件，按路由模板（而不是实际路径）统计，避免标签基数过大
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(res) => res.status().as_u16().to_string(),
+        Err(e) => e.as_response_error().status_code().as_u16().to_string(),
+    };
+    HTTP_REQUESTS.with_label_values(&[&route, &method, &status]).inc();
+    HTTP_DURATION
+        .with_label_values(&[&route, &method])
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+// 以 Prometheus 文本格式导出默认注册表中的全部指标
+pub fn render() -> Result<String, String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(buffer).map_err(|e| e.to_string())
+}